@@ -0,0 +1,50 @@
+// The data shared by the benchmarks and the compare_codecs example.
+
+use tsz::DataPoint;
+
+// The number of datapoints in each generated series.
+pub const NUM_DATAPOINTS: u64 = 10_000;
+
+// The representative time series the crate's tests use, which `datapoints` repeats.
+const DATA_1: &str = "1482892270,1.76
+1482892280,7.78
+1482892288,7.95
+1482892292,5.53
+1482892310,4.41
+1482892323,5.30
+1482892334,5.30
+1482892341,2.92
+1482892350,0.73
+1482892360,-1.33
+1482892370,-1.78
+1482892390,-12.45
+1482892401,-34.76
+1482892490,78.9
+1482892500,335.67
+1482892800,12908.12
+";
+
+pub const START_TIME: u64 = 1482892260;
+
+// datapoints repeats the deltas and values of DATA_1 until it has NUM_DATAPOINTS datapoints.
+pub fn datapoints() -> Vec<DataPoint> {
+    let rows: Vec<(u64, f64)> = DATA_1
+        .lines()
+        .map(|line| {
+            let substrings: Vec<&str> = line.split(',').collect();
+            let t = substrings[0].parse::<u64>().unwrap();
+            let v = substrings[1].parse::<f64>().unwrap();
+            (t - START_TIME, v)
+        })
+        .collect();
+
+    let period = rows[rows.len() - 1].0;
+
+    (0..NUM_DATAPOINTS)
+        .map(|i| {
+            let (offset, value) = rows[(i % rows.len() as u64) as usize];
+            let cycle = i / rows.len() as u64;
+            DataPoint::new(START_TIME + cycle * period + offset, value)
+        })
+        .collect()
+}
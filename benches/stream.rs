@@ -0,0 +1,112 @@
+#![cfg_attr(feature = "nightly", feature(test))]
+#![cfg(feature = "nightly")]
+
+extern crate test;
+extern crate tsz;
+
+mod data;
+
+use data::{datapoints, NUM_DATAPOINTS, START_TIME};
+use test::{black_box, Bencher};
+use tsz::stream::{BufferedReader, BufferedWriter, Read, Write};
+use tsz::{DataPoint, Encode, StdDecoder, StdEncoder, TimestampDecoder};
+
+fn encode(datapoints: &[DataPoint]) -> Box<[u8]> {
+    let mut encoder = StdEncoder::new(START_TIME, BufferedWriter::new());
+    for dp in datapoints {
+        encoder.encode(*dp);
+    }
+    encoder.close()
+}
+
+#[bench]
+fn encode_series(b: &mut Bencher) {
+    let datapoints = datapoints();
+    b.bytes = NUM_DATAPOINTS * 16;
+
+    b.iter(|| encode(black_box(&datapoints)));
+}
+
+#[bench]
+fn decode_series(b: &mut Bencher) {
+    let bytes = encode(&datapoints());
+    b.bytes = NUM_DATAPOINTS * 16;
+
+    b.iter(|| {
+        let r = BufferedReader::new(black_box(bytes.clone()));
+        for dp in StdDecoder::new(r) {
+            black_box(dp.unwrap());
+        }
+    });
+}
+
+#[bench]
+fn decode_into_columns(b: &mut Bencher) {
+    let bytes = encode(&datapoints());
+    b.bytes = NUM_DATAPOINTS * 16;
+
+    b.iter(|| {
+        let r = BufferedReader::new(black_box(bytes.clone()));
+        let mut decoder = StdDecoder::new(r);
+        let mut times = [0; 1024];
+        let mut values = [0.0; 1024];
+        while decoder.decode_into(&mut times, &mut values).unwrap() > 0 {
+            black_box((&times, &values));
+        }
+    });
+}
+
+#[bench]
+fn decode_timestamps(b: &mut Bencher) {
+    let bytes = encode(&datapoints());
+    b.bytes = NUM_DATAPOINTS * 8;
+
+    b.iter(|| {
+        let r = BufferedReader::new(black_box(bytes.clone()));
+        for time in TimestampDecoder::new(r) {
+            black_box(time.unwrap());
+        }
+    });
+}
+
+#[bench]
+fn count_datapoints(b: &mut Bencher) {
+    let bytes = encode(&datapoints());
+
+    b.iter(|| {
+        let r = BufferedReader::new(black_box(bytes.clone()));
+        TimestampDecoder::new(r).count().unwrap()
+    });
+}
+
+#[bench]
+fn write_bits(b: &mut Bencher) {
+    b.bytes = 10_000 * 8;
+
+    b.iter(|| {
+        let mut w = BufferedWriter::with_capacity(10_000 * 8);
+        for i in 0..10_000u64 {
+            w.write_bits(black_box(i), 64);
+        }
+        w.close()
+    });
+}
+
+#[bench]
+fn read_bits(b: &mut Bencher) {
+    let mut w = BufferedWriter::new();
+    for i in 0..10_000u64 {
+        w.write_bits(i, 64);
+    }
+    let bytes = w.close();
+    b.bytes = 10_000 * 8;
+
+    b.iter(|| {
+        let mut r = BufferedReader::new(black_box(bytes.clone()));
+        let mut sum = 0u64;
+        while let Ok(bits) = r.read_bits(64) {
+            sum = sum.wrapping_add(bits);
+        }
+        sum
+    });
+}
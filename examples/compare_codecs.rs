@@ -0,0 +1,70 @@
+// Prints the number of bits each value codec needs per `DataPoint` for a few kinds of series, to
+// help choose a codec for a family of metrics.
+//
+//     cargo run --example compare_codecs
+
+extern crate tsz;
+
+#[path = "../benches/data/mod.rs"]
+mod data;
+
+use data::{datapoints, NUM_DATAPOINTS, START_TIME};
+use tsz::stream::BufferedWriter;
+use tsz::{ChimpEncoder, DataPoint, Encode, StdEncoder};
+
+// generated returns a series sampled every 10 seconds whose values are given by `f`
+fn generated<F: Fn(u64) -> f64>(f: F) -> Vec<DataPoint> {
+    (0..NUM_DATAPOINTS)
+        .map(|i| DataPoint::new(START_TIME + 10 + i * 10, f(i)))
+        .collect()
+}
+
+fn bits_per_datapoint<E: Encode>(mut encoder: E, datapoints: &[DataPoint]) -> f64 {
+    for dp in datapoints {
+        encoder.encode(*dp);
+    }
+
+    let bytes = encoder.close();
+    (bytes.len() * 8) as f64 / datapoints.len() as f64
+}
+
+fn main() {
+    let series: Vec<(&str, Vec<DataPoint>)> = vec![
+        ("DATA_1 repeated", datapoints()),
+        ("constant gauge", generated(|_| 42.0)),
+        (
+            "gauge with 2 decimals",
+            generated(|i| ((i as f64 / 50.0).sin() * 5000.0).round() / 100.0),
+        ),
+        ("counter", generated(|i| (i * i % 977 + i * 1000) as f64)),
+        (
+            "full precision",
+            generated(|i| (i as f64 / 50.0).sin() * 50.0),
+        ),
+    ];
+
+    println!(
+        "{:<24}{:>10}{:>10}{:>10}",
+        "series", "gorilla", "chimp", "chimp128"
+    );
+
+    for (name, datapoints) in &series {
+        let gorilla = bits_per_datapoint(
+            StdEncoder::new(START_TIME, BufferedWriter::new()),
+            datapoints,
+        );
+        let chimp = bits_per_datapoint(
+            ChimpEncoder::new(START_TIME, BufferedWriter::new()),
+            datapoints,
+        );
+        let chimp_128 = bits_per_datapoint(
+            ChimpEncoder::new_128(START_TIME, BufferedWriter::new()),
+            datapoints,
+        );
+
+        println!(
+            "{:<24}{:>10.2}{:>10.2}{:>10.2}",
+            name, gorilla, chimp, chimp_128
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use decode;
+use DataPoint;
+
+/// BlockSummary
+///
+/// BlockSummary accumulates the count, sum, minimum, maximum, first and last of a sequence of
+/// `DataPoint`s one at a time, without keeping them. A `StdEncoder` can compute one for every
+/// `DataPoint` it encodes, see `StdEncoder::enable_summary`, so that queries whose range covers
+/// the whole block can be answered without decoding it, and `summarize` computes one for the
+/// `DataPoint`s of a decoder within a range.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct BlockSummary {
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    first: Option<DataPoint>, // earliest DataPoint
+    last: Option<DataPoint>,  // latest DataPoint
+}
+
+impl BlockSummary {
+    /// new creates a new BlockSummary of no `DataPoint`s
+    pub fn new() -> Self {
+        BlockSummary {
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            first: None,
+            last: None,
+        }
+    }
+
+    /// push adds `dp` to the summary
+    pub fn push(&mut self, dp: DataPoint) {
+        self.count += 1;
+        self.sum += dp.get_value();
+        self.min = self.min.min(dp.get_value());
+        self.max = self.max.max(dp.get_value());
+
+        if self
+            .first
+            .is_none_or(|first| dp.get_time() < first.get_time())
+        {
+            self.first = Some(dp);
+        }
+        if self
+            .last
+            .is_none_or(|last| dp.get_time() >= last.get_time())
+        {
+            self.last = Some(dp);
+        }
+    }
+
+    /// merge adds the `DataPoint`s summarized by `other` to the summary
+    pub fn merge(&mut self, other: &BlockSummary) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+
+        if let Some(first) = other.first {
+            if self.first.is_none_or(|dp| first.get_time() < dp.get_time()) {
+                self.first = Some(first);
+            }
+        }
+        if let Some(last) = other.last {
+            if self.last.is_none_or(|dp| last.get_time() >= dp.get_time()) {
+                self.last = Some(last);
+            }
+        }
+    }
+
+    /// Get the number of `DataPoint`s.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Get the sum of the values, which is zero if there are no `DataPoint`s.
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// Get the smallest value, ignoring NaNs, or None if there are no `DataPoint`s.
+    pub fn min(&self) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+
+        Some(self.min)
+    }
+
+    /// Get the largest value, ignoring NaNs, or None if there are no `DataPoint`s.
+    pub fn max(&self) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+
+        Some(self.max)
+    }
+
+    /// Get the mean of the values, or None if there are no `DataPoint`s.
+    pub fn mean(&self) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+
+        Some(self.sum / self.count as f64)
+    }
+
+    /// Get the earliest `DataPoint`.
+    pub fn first(&self) -> Option<DataPoint> {
+        self.first
+    }
+
+    /// Get the latest `DataPoint`.
+    pub fn last(&self) -> Option<DataPoint> {
+        self.last
+    }
+}
+
+impl Default for BlockSummary {
+    fn default() -> Self {
+        BlockSummary::new()
+    }
+}
+
+/// summarize returns a `BlockSummary` of the `DataPoint`s from `decoder` whose times are at least
+/// `start` and less than `end`. Since the `DataPoint`s of a stream are ordered by time it stops
+/// decoding at the first `DataPoint` at or after `end`.
+pub fn summarize<I>(decoder: I, start: u64, end: u64) -> Result<BlockSummary, decode::Error>
+where
+    I: IntoIterator<Item = Result<DataPoint, decode::Error>>,
+{
+    let mut summary = BlockSummary::new();
+    for dp in decoder {
+        let dp = dp?;
+        if dp.get_time() >= end {
+            break;
+        }
+        if dp.get_time() >= start {
+            summary.push(dp);
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{summarize, BlockSummary};
+    use stream::{BufferedReader, BufferedWriter};
+    use {DataPoint, Encode, StdDecoder, StdEncoder};
+
+    fn series() -> Vec<DataPoint> {
+        (0..100u64)
+            .map(|i| DataPoint::new(1000 + i * 10, ((i * 37) % 23) as f64 - 11.5))
+            .collect()
+    }
+
+    #[test]
+    fn summarize_range() {
+        let mut e = StdEncoder::new(1000, BufferedWriter::new());
+        e.enable_summary();
+        for &dp in &series() {
+            e.encode(dp);
+        }
+
+        // the summary computed by the encoder matches one computed from the points
+        let mut expected = BlockSummary::new();
+        for &dp in &series() {
+            expected.push(dp);
+        }
+        assert_eq!(*e.summary().unwrap(), expected);
+        assert_eq!(expected.count(), 100);
+        assert_eq!(expected.min(), Some(-11.5));
+        assert_eq!(expected.max(), Some(10.5));
+        assert_eq!(expected.first(), Some(series()[0]));
+        assert_eq!(expected.last(), Some(series()[99]));
+
+        let bytes = e.close();
+        let range = summarize(StdDecoder::new(BufferedReader::new(bytes)), 1200, 1500).unwrap();
+        let points = &series()[20..50];
+        assert_eq!(range.count(), 30);
+        assert_eq!(
+            range.sum(),
+            points.iter().map(|dp| dp.get_value()).sum::<f64>()
+        );
+        assert_eq!(range.first(), Some(points[0]));
+        assert_eq!(range.last(), Some(points[29]));
+        assert_eq!(range.mean(), Some(range.sum() / 30.0));
+
+        // merging summaries of parts of a series gives the summary of the whole series
+        let mut merged = BlockSummary::new();
+        for chunk in series().chunks(7).rev() {
+            let mut part = BlockSummary::new();
+            for &dp in chunk {
+                part.push(dp);
+            }
+            merged.merge(&part);
+        }
+        assert_eq!(merged, expected);
+
+        let empty = BlockSummary::new();
+        assert_eq!(empty.min(), None);
+        assert_eq!(empty.mean(), None);
+        assert_eq!(empty.first(), None);
+    }
+}
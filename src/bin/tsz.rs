@@ -0,0 +1,548 @@
+// A command-line tool for encoding, decoding and checking blocks written by `StdEncoder`.
+//
+//     cargo run --features cli -- encode points.csv -o block.tsz
+//     cargo run --features cli -- decode block.tsz --format json
+//     cargo run --features cli -- stats block.tsz
+//     cargo run --features cli -- verify block.tsz
+//     cargo run --features cli -- inspect block.tsz
+//
+// Blocks which begin with a `Header` are detected, and decoded with the time unit it records,
+// otherwise the time unit is given by `--unit`.
+
+extern crate tsz;
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+use tsz::header::{Header, ValueCodec, MAGIC};
+use tsz::inspect::Inspector;
+use tsz::stream::{self, BufferedWriter, Seek, SliceReader};
+use tsz::{DataPoint, Encode, StdDecoder, StdEncoder, TimeUnit};
+
+const USAGE: &str = "usage: tsz <command> [options] [input]
+
+commands:
+    encode    encode a CSV of time,value lines into a block
+    decode    decode a block into CSV or JSON
+    stats     print the number of points, time range, bits per point and bucket histogram
+    verify    decode a block fully and report where the first error is, and whether the
+              block is truncated or corrupt
+    inspect   print the bit offset, raw bits and meaning of each field of a block
+
+options:
+    -o, --output <path>     write to <path> instead of stdout
+    --unit <s|ms|us|ns>     time unit of the block, seconds by default
+    --start <time>          starting timestamp of an encoded block, by default the first
+                            time truncated to the hour
+    --header                write a header recording the time unit when encoding
+    --format <csv|json>     output format when decoding, csv by default
+
+The input is read from stdin if no path, or -, is given.";
+
+// Options are the parsed command-line arguments
+struct Options {
+    command: String,
+    input: Option<String>,
+    output: Option<String>,
+    unit: TimeUnit,
+    start: Option<u64>,
+    header: bool,
+    json: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut args = args.iter();
+    let command = args.next().ok_or("missing command")?.clone();
+
+    let mut options = Options {
+        command,
+        input: None,
+        output: None,
+        unit: TimeUnit::Second,
+        start: None,
+        header: false,
+        json: false,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("missing value for {}", arg))
+        };
+
+        match arg.as_str() {
+            "-o" | "--output" => options.output = Some(value()?),
+            "--unit" => {
+                options.unit = match value()?.as_str() {
+                    "s" => TimeUnit::Second,
+                    "ms" => TimeUnit::Millisecond,
+                    "us" => TimeUnit::Microsecond,
+                    "ns" => TimeUnit::Nanosecond,
+                    unit => return Err(format!("unknown time unit {}", unit)),
+                }
+            }
+            "--start" => {
+                let start = value()?;
+                let start = start
+                    .parse()
+                    .map_err(|_| format!("invalid start {}", start))?;
+                options.start = Some(start);
+            }
+            "--header" => options.header = true,
+            "--format" => {
+                options.json = match value()?.as_str() {
+                    "csv" => false,
+                    "json" => true,
+                    format => return Err(format!("unknown format {}", format)),
+                }
+            }
+            "-" => options.input = None,
+            arg if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            arg => {
+                if options.input.is_some() {
+                    return Err(format!("unexpected argument {}", arg));
+                }
+                options.input = Some(arg.to_string());
+            }
+        }
+    }
+
+    Ok(options)
+}
+
+fn read_input(options: &Options) -> Result<Vec<u8>, String> {
+    match options.input {
+        Some(ref path) => fs::read(path).map_err(|err| format!("failed to read {}: {}", path, err)),
+        None => {
+            let mut bytes = Vec::new();
+            io::stdin()
+                .read_to_end(&mut bytes)
+                .map_err(|err| format!("failed to read stdin: {}", err))?;
+            Ok(bytes)
+        }
+    }
+}
+
+fn write_output(options: &Options, bytes: &[u8]) -> Result<(), String> {
+    match options.output {
+        Some(ref path) => {
+            fs::write(path, bytes).map_err(|err| format!("failed to write {}: {}", path, err))
+        }
+        None => io::stdout()
+            .write_all(bytes)
+            .map_err(|err| format!("failed to write stdout: {}", err)),
+    }
+}
+
+// ticks_per_hour returns the number of units of time in an hour
+fn ticks_per_hour(unit: TimeUnit) -> u64 {
+    match unit {
+        TimeUnit::Second => 3600,
+        TimeUnit::Millisecond => 3_600_000,
+        TimeUnit::Microsecond => 3_600_000_000,
+        TimeUnit::Nanosecond => 3_600_000_000_000,
+    }
+}
+
+// parse_csv parses the DataPoints of a CSV of time,value lines, skipping blank lines and lines
+// which begin with #
+fn parse_csv(input: &str) -> Result<Vec<DataPoint>, String> {
+    let mut dps = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split(',');
+        let time = fields
+            .next()
+            .and_then(|time| time.trim().parse::<u64>().ok());
+        let value = fields
+            .next()
+            .and_then(|value| value.trim().parse::<f64>().ok());
+        match (time, value, fields.next()) {
+            (Some(time), Some(value), None) => dps.push(DataPoint::new(time, value)),
+            _ => return Err(format!("line {}: expected time,value", i + 1)),
+        }
+    }
+
+    Ok(dps)
+}
+
+fn encode(options: &Options) -> Result<(), String> {
+    let input = read_input(options)?;
+    let input = String::from_utf8(input).map_err(|_| "input is not valid UTF-8".to_string())?;
+    let dps = parse_csv(&input)?;
+
+    let start = options.start.unwrap_or_else(|| {
+        dps.first().map_or(0, |dp| {
+            dp.get_time() - dp.get_time() % ticks_per_hour(options.unit)
+        })
+    });
+
+    let w = BufferedWriter::new();
+    let mut encoder = if options.header {
+        StdEncoder::with_header(start, options.unit, w)
+    } else {
+        StdEncoder::with_time_unit(start, options.unit, w)
+    };
+
+    for (i, dp) in dps.iter().enumerate() {
+        encoder
+            .try_encode(*dp)
+            .map_err(|err| format!("point {} at {}: {}", i, dp.get_time(), err))?;
+    }
+
+    let bytes = encoder
+        .try_close()
+        .map_err(|err| format!("failed to close block: {}", err))?;
+    write_output(options, &bytes)
+}
+
+// decoder returns a decoder for `bytes`, the time unit of the block and whether it begins with a
+// header, which is read if so
+fn decoder<'a>(
+    bytes: &'a [u8],
+    options: &Options,
+) -> Result<(StdDecoder<SliceReader<'a>>, TimeUnit, bool), String> {
+    if bytes.len() >= 4 && bytes[..4] == MAGIC.to_be_bytes() {
+        let header = Header::read(&mut SliceReader::new(bytes))
+            .map_err(|err| format!("invalid header: {}", err))?;
+        if header.codec() != ValueCodec::Gorilla {
+            return Err(format!(
+                "block values were compressed with {:?}",
+                header.codec()
+            ));
+        }
+
+        // the decoder reads the header again so that the offsets in its errors include it
+        let decoder = StdDecoder::with_header(SliceReader::new(bytes))
+            .map_err(|err| format!("invalid header: {}", err))?;
+        return Ok((decoder, header.time_unit(), true));
+    }
+
+    Ok((
+        StdDecoder::with_time_unit(SliceReader::new(bytes), options.unit),
+        options.unit,
+        false,
+    ))
+}
+
+fn decode(options: &Options) -> Result<(), String> {
+    let bytes = read_input(options)?;
+    let (decoder, _, _) = decoder(&bytes, options)?;
+
+    let mut out = String::new();
+    if options.json {
+        out.push('[');
+    }
+
+    for (i, dp) in decoder.enumerate() {
+        let dp = dp.map_err(|err| format!("point {}: {}", i, err))?;
+        if options.json {
+            if i > 0 {
+                out.push(',');
+            }
+            // JSON has no representation for NaN or infinity
+            let value = if dp.get_value().is_finite() {
+                dp.get_value().to_string()
+            } else {
+                "null".to_string()
+            };
+            out.push_str(&format!(
+                "\n  {{\"time\": {}, \"value\": {}}}",
+                dp.get_time(),
+                value
+            ));
+        } else {
+            out.push_str(&format!("{},{}\n", dp.get_time(), dp.get_value()));
+        }
+    }
+
+    if options.json {
+        out.push_str("\n]\n");
+    }
+
+    write_output(options, out.as_bytes())
+}
+
+// bucket returns which bucket a delta of delta is stored in, where 0 is a single zero bit and
+// 1 to 4 are the buckets with the control bits 10, 110, 1110 and 1111
+fn bucket(dod: i128, unit: TimeUnit) -> usize {
+    if dod == 0 {
+        return 0;
+    }
+
+    for (i, &len) in unit.bucket_lens().iter().enumerate() {
+        let bound = 1i128 << (len - 1);
+        if dod > -bound && dod <= bound {
+            return i + 1;
+        }
+    }
+
+    4
+}
+
+// histogram counts the delta of deltas of `times`, the timestamps of a block which starts at
+// `start`, in each bucket. The first delta is from the start of the block, as the encoder takes
+// it, so the first delta of delta is that of the second timestamp.
+fn histogram(start: u64, times: &[u64], unit: TimeUnit) -> [u64; 5] {
+    let mut buckets = [0; 5];
+    let mut last = None;
+    let mut delta = None;
+
+    for &time in times {
+        if let Some(last) = last {
+            let d = i128::from(time) - i128::from(last);
+            if let Some(delta) = delta {
+                buckets[bucket(d - delta, unit)] += 1;
+            }
+            delta = Some(d);
+        } else {
+            delta = Some(i128::from(time) - i128::from(start));
+        }
+        last = Some(time);
+    }
+
+    buckets
+}
+
+// block_start reads the starting timestamp of the block in `bytes`, which follows its header if
+// it has one
+fn block_start(bytes: &[u8], header: bool) -> Result<u64, String> {
+    let mut r = SliceReader::new(bytes);
+    if header {
+        Header::read(&mut r).map_err(|err| format!("invalid header: {}", err))?;
+    }
+
+    stream::Read::read_bits(&mut r, 64)
+        .map_err(|err| format!("failed to read block start: {}", err))
+}
+
+fn stats(options: &Options) -> Result<(), String> {
+    let bytes = read_input(options)?;
+    let (decoder, unit, header) = decoder(&bytes, options)?;
+
+    let mut times = Vec::new();
+    for (i, dp) in decoder.enumerate() {
+        let dp = dp.map_err(|err| format!("point {}: {}", i, err))?;
+        times.push(dp.get_time());
+    }
+
+    let count = times.len() as u64;
+    let (first, last) = (times.first(), times.last());
+    let buckets = if times.is_empty() {
+        [0; 5]
+    } else {
+        histogram(block_start(&bytes, header)?, &times, unit)
+    };
+
+    let bits = bytes.len() as u64 * 8;
+    let mut out = String::new();
+    out.push_str(&format!("header:          {}\n", header));
+    out.push_str(&format!("time unit:       {:?}\n", unit));
+    out.push_str(&format!("points:          {}\n", count));
+    if let (Some(first), Some(last)) = (first, last) {
+        out.push_str(&format!("time range:      {} - {}\n", first, last));
+    }
+    out.push_str(&format!("bits:            {}\n", bits));
+    if count > 0 {
+        out.push_str(&format!(
+            "bits per point:  {:.2}\n",
+            bits as f64 / count as f64
+        ));
+    }
+
+    out.push_str("delta of delta buckets:\n");
+    let lens = unit.bucket_lens();
+    let labels = [
+        "0".to_string(),
+        format!("10   ({} bits)", lens[0]),
+        format!("110  ({} bits)", lens[1]),
+        format!("1110 ({} bits)", lens[2]),
+        format!("1111 ({} bits)", lens[3]),
+    ];
+    for (label, count) in labels.iter().zip(&buckets) {
+        out.push_str(&format!("    {:<16}{}\n", label, count));
+    }
+
+    write_output(options, out.as_bytes())
+}
+
+fn verify(options: &Options) -> Result<bool, String> {
+    let bytes = read_input(options)?;
+    let (mut decoder, _, _) = decoder(&bytes, options)?;
+
+    let mut count = 0u64;
+    loop {
+        match decoder.next() {
+            Some(Ok(_)) => count += 1,
+            Some(Err(err)) => {
+                let out = format!("error after {} points: {}\n", count, err);
+                write_output(options, out.as_bytes())?;
+                return Ok(false);
+            }
+            None => break,
+        }
+    }
+
+    let out = format!(
+        "ok: {} points in {} bits\n",
+        count,
+        decoder.get_ref().bit_position()
+    );
+    write_output(options, out.as_bytes())?;
+    Ok(true)
+}
+
+fn inspect(options: &Options) -> Result<bool, String> {
+    let bytes = read_input(options)?;
+    let r = SliceReader::new(&bytes);
+    let inspector = if bytes.len() >= 4 && bytes[..4] == MAGIC.to_be_bytes() {
+        Inspector::with_header(r)
+    } else {
+        Inspector::with_time_unit(r, options.unit)
+    };
+
+    let mut out = String::new();
+    let mut ok = true;
+    for event in inspector {
+        match event {
+            Ok(event) => out.push_str(&format!("{}\n", event)),
+            Err(err) => {
+                out.push_str(&format!("error: {}\n", err));
+                ok = false;
+            }
+        }
+    }
+
+    write_output(options, out.as_bytes())?;
+    Ok(ok)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args[0] == "-h" || args[0] == "--help" {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("tsz: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let result = match options.command.as_str() {
+        "encode" => encode(&options).map(|_| true),
+        "decode" => decode(&options).map(|_| true),
+        "stats" => stats(&options).map(|_| true),
+        "verify" => verify(&options),
+        "inspect" => inspect(&options),
+        command => {
+            eprintln!("tsz: unknown command {}\n\n{}", command, USAGE);
+            process::exit(2);
+        }
+    };
+
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("tsz: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bucket, histogram, parse_args, parse_csv, Options};
+    use tsz::{DataPoint, TimeUnit};
+
+    fn args(args: &[&str]) -> Result<Options, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn parse_options() {
+        let options = args(&[
+            "encode", "in.csv", "-o", "out.tsz", "--unit", "ms", "--start", "3600", "--header",
+        ])
+        .unwrap();
+        assert_eq!(options.command, "encode");
+        assert_eq!(options.input, Some("in.csv".to_string()));
+        assert_eq!(options.output, Some("out.tsz".to_string()));
+        assert_eq!(options.unit, TimeUnit::Millisecond);
+        assert_eq!(options.start, Some(3600));
+        assert!(options.header);
+        assert!(!options.json);
+
+        let options = args(&["decode", "-", "--format", "json"]).unwrap();
+        assert_eq!(options.input, None);
+        assert_eq!(options.unit, TimeUnit::Second);
+        assert!(options.json);
+
+        assert!(args(&[]).is_err());
+        assert!(args(&["decode", "--unit"]).is_err());
+        assert!(args(&["decode", "--unit", "h"]).is_err());
+        assert!(args(&["decode", "--start", "soon"]).is_err());
+        assert!(args(&["decode", "--format", "xml"]).is_err());
+        assert!(args(&["decode", "--verbose"]).is_err());
+        assert!(args(&["decode", "a.tsz", "b.tsz"]).is_err());
+    }
+
+    #[test]
+    fn parse_points() {
+        let dps = parse_csv("# time,value\n3600,1\n\n 3610 , -2.5 \n").unwrap();
+        assert_eq!(
+            dps,
+            vec![DataPoint::new(3600, 1.0), DataPoint::new(3610, -2.5)]
+        );
+
+        assert_eq!(parse_csv("").unwrap(), vec![]);
+        assert_eq!(
+            parse_csv("3600,1\n3610").err().unwrap(),
+            "line 2: expected time,value"
+        );
+        assert!(parse_csv("3600,1,2").is_err());
+        assert!(parse_csv("-1,1").is_err());
+        assert!(parse_csv("3600,one").is_err());
+    }
+
+    #[test]
+    fn buckets() {
+        let unit = TimeUnit::Second;
+        assert_eq!(bucket(0, unit), 0);
+        assert_eq!(bucket(-63, unit), 1);
+        assert_eq!(bucket(64, unit), 1);
+        assert_eq!(bucket(-64, unit), 2);
+        assert_eq!(bucket(65, unit), 2);
+        assert_eq!(bucket(256, unit), 2);
+        assert_eq!(bucket(257, unit), 3);
+        assert_eq!(bucket(2048, unit), 3);
+        assert_eq!(bucket(2049, unit), 4);
+        assert_eq!(bucket(-(1 << 40), unit), 4);
+
+        assert_eq!(bucket(512, TimeUnit::Millisecond), 1);
+        assert_eq!(bucket(513, TimeUnit::Millisecond), 2);
+    }
+
+    #[test]
+    fn histogram_includes_first_delta_of_delta() {
+        let unit = TimeUnit::Second;
+
+        // the first delta is 0 from the start of the block, so the second point has a delta of
+        // delta of 10 and the third of 0
+        assert_eq!(histogram(3600, &[3600, 3610, 3620], unit), [1, 1, 0, 0, 0]);
+        assert_eq!(histogram(3600, &[3610, 3620, 3630], unit), [2, 0, 0, 0, 0]);
+        assert_eq!(histogram(0, &[3600], unit), [0; 5]);
+        assert_eq!(histogram(0, &[], unit), [0; 5]);
+    }
+}
@@ -0,0 +1,402 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+use std::{error, fmt};
+
+use agg::BlockSummary;
+use decode;
+use encode;
+use store::Block;
+use stream::{BufferedWriter, Read, SliceReader};
+use {DataPoint, Encode, StdDecoder, StdEncoder, TimeUnit};
+
+/// Error
+///
+/// Error encapsulates the potential errors that can be encountered when merging streams
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// One of the streams being merged could not be decoded.
+    Decode(decode::Error),
+    /// The merged `DataPoint`s could not be encoded into a single stream.
+    Encode(encode::Error),
+    /// More than one `DataPoint` has the time and the policy is `DuplicatePolicy::Error`.
+    Duplicate(u64),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Decode(ref err) => write!(f, "Decode error: {}", err),
+            Error::Encode(ref err) => write!(f, "Encode error: {}", err),
+            Error::Duplicate(time) => write!(f, "Found more than one DataPoint at {}", time),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Decode(ref err) => Some(err),
+            Error::Encode(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<decode::Error> for Error {
+    fn from(err: decode::Error) -> Error {
+        Error::Decode(err)
+    }
+}
+
+impl From<encode::Error> for Error {
+    fn from(err: encode::Error) -> Error {
+        Error::Encode(err)
+    }
+}
+
+/// DuplicatePolicy
+///
+/// DuplicatePolicy decides which `DataPoint` is kept when the streams being merged hold more than
+/// one `DataPoint` with the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Keep the `DataPoint` from the earliest stream, in the order the streams were given.
+    KeepFirst,
+    /// Keep the `DataPoint` from the latest stream, so later streams overwrite earlier ones.
+    KeepLast,
+    /// Fail the merge with `Error::Duplicate`.
+    Error,
+}
+
+// Head is the next DataPoint of one of the streams being merged, heads are ordered by time and
+// then by the order of their streams so that duplicates come out in the order of their streams
+#[derive(Debug)]
+struct Head {
+    dp: DataPoint,
+    source: usize,
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.dp.get_time(), self.source).cmp(&(other.dp.get_time(), other.source))
+    }
+}
+
+// heads are equal when they are ordered equally, which ignores their values, so that equality
+// agrees with Ord
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// merge merges the `DataPoint`s of `blocks`, each of which must have been written by a
+/// `StdEncoder` whose timestamps are in seconds, into a single stream starting at the earliest
+/// starting timestamp of the blocks
+pub fn merge(blocks: &[&[u8]], policy: DuplicatePolicy) -> Result<Box<[u8]>, Error> {
+    merge_with_time_unit(blocks, TimeUnit::Second, policy)
+}
+
+/// merge_with_time_unit merges the `DataPoint`s of `blocks` like `merge`, for blocks whose
+/// timestamps have a resolution of `unit`
+pub fn merge_with_time_unit(
+    blocks: &[&[u8]],
+    unit: TimeUnit,
+    policy: DuplicatePolicy,
+) -> Result<Box<[u8]>, Error> {
+    let mut start = None;
+    for bytes in blocks {
+        let time = SliceReader::new(bytes).read_bits(64).map_err(|err| {
+            let context = decode::Context::new(0, 0, decode::FieldKind::StartTime);
+            decode::Error::at(err, context)
+        })?;
+        start = Some(start.map_or(time, |start: u64| start.min(time)));
+    }
+
+    merge_streams(blocks, start.unwrap_or(0), unit, policy).map(|(bytes, _)| bytes)
+}
+
+// merge_streams performs a k-way merge of the DataPoints of `blocks` into a stream starting at
+// `start`, returning the stream and a summary of the DataPoints in it
+fn merge_streams(
+    blocks: &[&[u8]],
+    start: u64,
+    unit: TimeUnit,
+    policy: DuplicatePolicy,
+) -> Result<(Box<[u8]>, BlockSummary), Error> {
+    let mut decoders: Vec<_> = blocks
+        .iter()
+        .map(|bytes| StdDecoder::with_time_unit(SliceReader::new(bytes), unit))
+        .collect();
+
+    let mut heads = BinaryHeap::with_capacity(decoders.len());
+    for (source, decoder) in decoders.iter_mut().enumerate() {
+        if let Some(dp) = decoder.next() {
+            heads.push(Reverse(Head { dp: dp?, source }));
+        }
+    }
+
+    let mut encoder = StdEncoder::with_time_unit(start, unit, BufferedWriter::new());
+    encoder.enable_summary();
+    let mut pending: Option<DataPoint> = None;
+
+    while let Some(Reverse(head)) = heads.pop() {
+        if let Some(dp) = decoders[head.source].next() {
+            heads.push(Reverse(Head {
+                dp: dp?,
+                source: head.source,
+            }));
+        }
+
+        pending = match pending {
+            Some(prev) if prev.get_time() == head.dp.get_time() => match policy {
+                DuplicatePolicy::KeepFirst => Some(prev),
+                DuplicatePolicy::KeepLast => Some(head.dp),
+                DuplicatePolicy::Error => return Err(Error::Duplicate(prev.get_time())),
+            },
+            Some(prev) => {
+                encoder.try_encode(prev)?;
+                Some(head.dp)
+            }
+            None => Some(head.dp),
+        };
+    }
+
+    if let Some(dp) = pending {
+        encoder.try_encode(dp)?;
+    }
+
+    let summary = encoder.summary().copied().unwrap_or_default();
+    Ok((encoder.try_close()?, summary))
+}
+
+/// Compactor
+///
+/// Compactor merges the closed `Block`s of a series into fewer, larger blocks. Blocks whose
+/// windows overlap are always merged, so that no two blocks it returns overlap, and blocks whose
+/// windows are adjacent are merged while the merged block would hold at most `max_points`
+/// `DataPoint`s.
+#[derive(Debug, Clone, Copy)]
+pub struct Compactor {
+    max_points: u64,
+    unit: TimeUnit,
+    policy: DuplicatePolicy,
+}
+
+impl Compactor {
+    /// new creates a new Compactor for blocks whose timestamps are in seconds
+    pub fn new(max_points: u64, policy: DuplicatePolicy) -> Self {
+        Compactor::with_time_unit(max_points, policy, TimeUnit::Second)
+    }
+
+    /// with_time_unit creates a new Compactor for blocks whose timestamps have a resolution of
+    /// `unit`
+    pub fn with_time_unit(max_points: u64, policy: DuplicatePolicy, unit: TimeUnit) -> Self {
+        Compactor {
+            max_points,
+            unit,
+            policy,
+        }
+    }
+
+    /// compact returns the blocks of a series after merging them, ordered by start. Blocks which
+    /// are not merged with any other are returned as they are.
+    pub fn compact(&self, blocks: &[Block]) -> Result<Vec<Block>, Error> {
+        let mut blocks: Vec<&Block> = blocks.iter().collect();
+        blocks.sort_by_key(|block| block.start());
+
+        let mut compacted = Vec::new();
+        let mut group: Vec<&Block> = Vec::new();
+        let mut end = 0;
+        let mut points = 0;
+
+        for block in blocks {
+            let overlaps = block.start() < end;
+            let adjacent = block.start() == end && points + block.points() <= self.max_points;
+            if !group.is_empty() && !overlaps && !adjacent {
+                compacted.push(self.merge_group(&group)?);
+                group.clear();
+            }
+
+            if group.is_empty() {
+                end = block.end();
+                points = 0;
+            }
+            group.push(block);
+            end = end.max(block.end());
+            points += block.points();
+        }
+
+        if !group.is_empty() {
+            compacted.push(self.merge_group(&group)?);
+        }
+
+        Ok(compacted)
+    }
+
+    // merge_group merges a group of blocks ordered by start into a single block
+    fn merge_group(&self, group: &[&Block]) -> Result<Block, Error> {
+        if group.len() == 1 {
+            return Ok(group[0].clone());
+        }
+
+        let start = group[0].start();
+        let end = group.iter().map(|block| block.end()).max().unwrap_or(start);
+        let streams: Vec<&[u8]> = group.iter().map(|block| block.bytes()).collect();
+        let (bytes, summary) = merge_streams(&streams, start, self.unit, self.policy)?;
+
+        Ok(Block::new(start, end, Arc::from(bytes), summary))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge, Compactor, DuplicatePolicy, Error, Head};
+    use decode;
+    use decode::decode_all;
+    use store::SeriesStore;
+    use stream::BufferedWriter;
+    use {DataPoint, Encode, StdEncoder};
+
+    fn encode(start: u64, dps: &[(u64, f64)]) -> Box<[u8]> {
+        let mut e = StdEncoder::new(start, BufferedWriter::new());
+        for &(time, value) in dps {
+            e.encode(DataPoint::new(time, value));
+        }
+        e.close()
+    }
+
+    #[test]
+    fn head_eq_agrees_with_ord() {
+        let head = |time, value, source| Head {
+            dp: DataPoint::new(time, value),
+            source,
+        };
+
+        // duplicates from the same stream are equal whatever their values
+        assert_eq!(head(10, 1.0, 0), head(10, 2.0, 0));
+        assert!(head(10, 1.0, 0) < head(10, 1.0, 1));
+        assert!(head(10, 1.0, 1) < head(20, 1.0, 0));
+    }
+
+    fn datapoints(dps: &[(u64, f64)]) -> Vec<DataPoint> {
+        dps.iter()
+            .map(|&(time, value)| DataPoint::new(time, value))
+            .collect()
+    }
+
+    #[test]
+    fn merge_overlapping_streams() {
+        let a = encode(1000, &[(1010, 1.0), (1030, 3.0), (1050, 5.0)]);
+        let b = encode(1000, &[(1020, 2.0), (1030, 30.0), (1060, 6.0)]);
+        let c = encode(990, &[(1000, 0.5), (1050, 50.0), (1070, 7.0)]);
+        let blocks: Vec<&[u8]> = vec![&a, &b, &c];
+
+        let first = merge(&blocks, DuplicatePolicy::KeepFirst).unwrap();
+        assert_eq!(
+            decode_all(&first).unwrap(),
+            datapoints(&[
+                (1000, 0.5),
+                (1010, 1.0),
+                (1020, 2.0),
+                (1030, 3.0),
+                (1050, 5.0),
+                (1060, 6.0),
+                (1070, 7.0),
+            ])
+        );
+
+        let last = merge(&blocks, DuplicatePolicy::KeepLast).unwrap();
+        assert_eq!(
+            decode_all(&last).unwrap(),
+            datapoints(&[
+                (1000, 0.5),
+                (1010, 1.0),
+                (1020, 2.0),
+                (1030, 30.0),
+                (1050, 50.0),
+                (1060, 6.0),
+                (1070, 7.0),
+            ])
+        );
+
+        assert_eq!(
+            merge(&blocks, DuplicatePolicy::Error).err().unwrap(),
+            Error::Duplicate(1030)
+        );
+
+        // a stream without a starting timestamp cannot be merged
+        assert_eq!(
+            merge(&[&a, &c[..0]], DuplicatePolicy::Error).err().unwrap(),
+            Error::Decode(decode::Error::Truncated(decode::Context::new(
+                0,
+                0,
+                decode::FieldKind::StartTime
+            )))
+        );
+
+        // streams without DataPoints contribute nothing
+        let empty = encode(0, &[]);
+        let merged = merge(&[&a, &empty], DuplicatePolicy::Error).unwrap();
+        assert_eq!(decode_all(&merged).unwrap(), decode_all(&a).unwrap());
+        assert!(decode_all(&merge(&[], DuplicatePolicy::Error).unwrap())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn compact_blocks() {
+        let mut store = SeriesStore::new(600).unwrap();
+        for i in 0..360u64 {
+            store
+                .insert("cpu", DataPoint::new(i * 20 + i % 3, i as f64))
+                .unwrap();
+        }
+        // a late block which overlaps the second window
+        let late = encode(600, &[(605, -1.0), (905, -2.0)]);
+        let mut late_store = SeriesStore::new(600).unwrap();
+        for dp in decode_all(&late).unwrap() {
+            late_store.insert("cpu", dp).unwrap();
+        }
+        late_store.insert("cpu", DataPoint::new(1200, 0.0)).unwrap();
+
+        let mut blocks = store.blocks("cpu").to_vec();
+        blocks.extend_from_slice(late_store.blocks("cpu"));
+        assert_eq!(blocks.len(), 12);
+
+        // each window holds 30 DataPoints, so 3 windows are merged into each block, except for
+        // the second which also holds the late block
+        let compactor = Compactor::new(90, DuplicatePolicy::KeepLast);
+        let compacted = compactor.compact(&blocks).unwrap();
+        assert_eq!(
+            compacted
+                .iter()
+                .map(|block| (block.start(), block.end(), block.points()))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, 1200, 62),
+                (1200, 3000, 90),
+                (3000, 4800, 90),
+                (4800, 6600, 90),
+            ]
+        );
+
+        let mut expected = store.query("cpu", 0, 6600).unwrap();
+        expected.extend(datapoints(&[(605, -1.0), (905, -2.0)]));
+        expected.sort();
+        let actual: Vec<DataPoint> = compacted
+            .iter()
+            .flat_map(|block| decode_all(block.bytes()).unwrap())
+            .collect();
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,288 @@
+use std::iter::FusedIterator;
+
+use decode::cursor::Cursor;
+use decode::timestamp::TimestampReader;
+use decode::{Decode, Error, FieldKind};
+use encode::chimp_encoder::{LEADING_ZEROES, PREVIOUS_VALUES};
+use header::{Header, ValueCodec};
+use stream::Read;
+use {DataPoint, TimeUnit};
+
+/// ChimpDecoder
+///
+/// ChimpDecoder is used to decode `DataPoint`s written by a `ChimpEncoder`
+#[derive(Debug)]
+pub struct ChimpDecoder<T: Read> {
+    timestamps: TimestampReader, // current time and time delta
+    value_bits: u64,             // current float value as bits
+
+    leading_zeroes: u32, // leading zeroes stored by the last xor with a 11 flag
+
+    // ring buffer of the previous values and the position of the current value in it, only used
+    // by the Chimp128 scheme
+    values: Option<Box<[u64; PREVIOUS_VALUES]>>,
+    current: usize,
+
+    first: bool, // will next DataPoint be the first DataPoint decoded
+    done: bool,
+
+    r: Cursor<T>,
+}
+
+impl<T> ChimpDecoder<T>
+where
+    T: Read,
+{
+    /// new creates a new ChimpDecoder which will read bytes written using the Chimp scheme from r
+    pub fn new(r: T) -> Self {
+        ChimpDecoder::with_time_unit(r, TimeUnit::Second)
+    }
+
+    /// new_128 creates a new ChimpDecoder which will read bytes written using the Chimp128 scheme
+    /// from r
+    pub fn new_128(r: T) -> Self {
+        ChimpDecoder::with_time_unit_128(r, TimeUnit::Second)
+    }
+
+    /// with_time_unit creates a new ChimpDecoder which will read bytes written using the Chimp
+    /// scheme from r, whose timestamps have a resolution of `unit`
+    pub fn with_time_unit(r: T, unit: TimeUnit) -> Self {
+        ChimpDecoder::create(r, unit, false)
+    }
+
+    /// with_time_unit_128 creates a new ChimpDecoder like `with_time_unit`, but for bytes written
+    /// using the Chimp128 scheme
+    pub fn with_time_unit_128(r: T, unit: TimeUnit) -> Self {
+        ChimpDecoder::create(r, unit, true)
+    }
+
+    /// with_header creates a new ChimpDecoder which will read bytes from r, which must begin with
+    /// the `Header` written by `ChimpEncoder::with_header` or `ChimpEncoder::with_header_128`.
+    /// The scheme and time unit are taken from the header.
+    pub fn with_header(mut r: T) -> Result<Self, Error> {
+        let header = Header::read(&mut r)?;
+        let window = match header.codec() {
+            ValueCodec::Chimp => false,
+            ValueCodec::Chimp128 => true,
+            codec => return Err(Error::UnexpectedCodec(codec)),
+        };
+
+        let mut decoder = ChimpDecoder::create(r, header.time_unit(), window);
+        decoder.r.position = u64::from(Header::LEN);
+        Ok(decoder)
+    }
+
+    fn create(r: T, unit: TimeUnit, window: bool) -> Self {
+        ChimpDecoder {
+            timestamps: TimestampReader::new(unit),
+            value_bits: 0,
+            leading_zeroes: 0,
+            values: if window {
+                Some(Box::new([0; PREVIOUS_VALUES]))
+            } else {
+                None
+            },
+            current: 0,
+            first: true,
+            done: false,
+            r: Cursor::new(r, 0),
+        }
+    }
+
+    fn read_first_value(&mut self) -> Result<u64, Error> {
+        self.value_bits = self.r.read_bits(64, FieldKind::FirstValue)?;
+        if let Some(ref mut values) = self.values {
+            values[0] = self.value_bits;
+        }
+
+        Ok(self.value_bits)
+    }
+
+    fn read_next_value(&mut self) -> Result<u64, Error> {
+        let flag = self.r.read_bits(2, FieldKind::ValueControl)?;
+
+        // the Chimp128 scheme stores which previous value the xor is with for the 00 and 01
+        // flags, otherwise the xor is with the immediately previous value
+        let previous = match self.values {
+            Some(ref values) if flag < 0b10 => {
+                let index = self.r.read_bits(7, FieldKind::PreviousValue)?;
+                values[index as usize % PREVIOUS_VALUES]
+            }
+            _ => self.value_bits,
+        };
+
+        let xor = match flag {
+            0b00 => 0,
+            0b01 => {
+                let leading_zeroes =
+                    LEADING_ZEROES[self.r.read_bits(3, FieldKind::LeadingZeroes)? as usize];
+                let significant_digits = self.r.read_bits(6, FieldKind::SignificantBits)? as u32;
+                let trailing_zeroes = 64u32
+                    .saturating_sub(leading_zeroes)
+                    .saturating_sub(significant_digits);
+                self.r.read_bits(significant_digits, FieldKind::Xor)? << trailing_zeroes
+            }
+            flag => {
+                if flag == 0b11 {
+                    self.leading_zeroes =
+                        LEADING_ZEROES[self.r.read_bits(3, FieldKind::LeadingZeroes)? as usize];
+                }
+                self.r.read_bits(64 - self.leading_zeroes, FieldKind::Xor)?
+            }
+        };
+
+        self.value_bits = previous ^ xor;
+        if let Some(ref mut values) = self.values {
+            self.current = (self.current + 1) % PREVIOUS_VALUES;
+            values[self.current] = self.value_bits;
+        }
+
+        Ok(self.value_bits)
+    }
+
+    // read_point reads the next DataPoint, see `Decode::next`
+    fn read_point(&mut self) -> Result<DataPoint, Error> {
+        let time;
+        let value_bits = if self.first {
+            self.first = false;
+            time = self.timestamps.read_first(&mut self.r)?;
+            self.read_first_value()?
+        } else {
+            time = self.timestamps.read_next(&mut self.r)?;
+            self.read_next_value()?
+        };
+
+        let value = f64::from_bits(value_bits);
+        self.r.point += 1;
+
+        Ok(DataPoint::new(time, value))
+    }
+}
+
+impl<T> Decode for ChimpDecoder<T>
+where
+    T: Read,
+{
+    fn next(&mut self) -> Result<DataPoint, Error> {
+        if self.done {
+            return Err(Error::EndOfStream);
+        }
+
+        // the stream may be left in the middle of a DataPoint after an error, so any error ends it
+        let dp = self.read_point();
+        if dp.is_err() {
+            self.done = true;
+        }
+        dp
+    }
+}
+
+impl<T> Iterator for ChimpDecoder<T>
+where
+    T: Read,
+{
+    type Item = Result<DataPoint, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match Decode::next(self) {
+            Ok(dp) => Some(Ok(dp)),
+            Err(Error::EndOfStream) => None,
+            // the decoder cannot recover from an error, so it ends the stream after reporting it
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+impl<T> FusedIterator for ChimpDecoder<T> where T: Read {}
+
+#[cfg(test)]
+mod tests {
+    use super::ChimpDecoder;
+    use decode::Error;
+    use encode::chimp_encoder::ChimpEncoder;
+    use header::ValueCodec;
+    use stream::{BufferedReader, BufferedWriter};
+    use {DataPoint, Encode, StdEncoder, TimeUnit};
+
+    fn series() -> Vec<DataPoint> {
+        let start = 1482268055;
+        let values = [
+            1.24, 1.24, 1.98, 3.7, -1.5, 0.0, 0.0, 1e-300, 1e300, 12.5, 12.75, 12.5, 1.98, 3.7,
+        ];
+
+        (0..200)
+            .map(|i| {
+                let value = values[i % values.len()] + (i / values.len()) as f64;
+                DataPoint::new(start + 10 + i as u64 * 10 + (i as u64 % 3), value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decode_chimp_datapoints() {
+        let start = 1482268055;
+        let mut e = ChimpEncoder::new(start, BufferedWriter::new());
+        for &dp in &series() {
+            e.encode(dp);
+        }
+
+        let r = BufferedReader::new(e.close());
+        let decoder = ChimpDecoder::new(r);
+        let actual: Result<Vec<DataPoint>, Error> = decoder.collect();
+
+        assert_eq!(actual.unwrap(), series());
+    }
+
+    #[test]
+    fn decode_chimp_128_datapoints() {
+        let start = 1482268055;
+        let mut e = ChimpEncoder::new_128(start, BufferedWriter::new());
+        for &dp in &series() {
+            e.encode(dp);
+        }
+
+        let r = BufferedReader::new(e.close());
+        let decoder = ChimpDecoder::new_128(r);
+        let actual: Result<Vec<DataPoint>, Error> = decoder.collect();
+
+        assert_eq!(actual.unwrap(), series());
+    }
+
+    #[test]
+    fn decode_with_header() {
+        let start = 1482268055000;
+        let dps: Vec<DataPoint> = series()
+            .into_iter()
+            .map(|dp| DataPoint::new(dp.time * 1000 + 7, dp.value))
+            .collect();
+
+        for &window in &[false, true] {
+            let w = BufferedWriter::new();
+            let mut e = if window {
+                ChimpEncoder::with_header_128(start, TimeUnit::Millisecond, w)
+            } else {
+                ChimpEncoder::with_header(start, TimeUnit::Millisecond, w)
+            };
+            for &dp in &dps {
+                e.encode(dp);
+            }
+
+            let r = BufferedReader::new(e.close());
+            let decoder = ChimpDecoder::with_header(r).unwrap();
+            let actual: Result<Vec<DataPoint>, Error> = decoder.collect();
+
+            assert_eq!(actual.unwrap(), dps);
+        }
+    }
+
+    #[test]
+    fn with_header_rejects_gorilla_stream() {
+        let e = StdEncoder::with_header(1482268055, TimeUnit::Second, BufferedWriter::new());
+        let r = BufferedReader::new(e.close());
+
+        assert_eq!(
+            ChimpDecoder::with_header(r).err().unwrap(),
+            Error::UnexpectedCodec(ValueCodec::Gorilla)
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use decode::{Context, Error, FieldKind};
+use stream::{Read, Seek};
+use Bit;
+
+// Cursor wraps the reader of a decoder to count the bits read from it and the DataPoints decoded,
+// so that an error can tell which field of which DataPoint was being read and where.
+#[derive(Debug)]
+pub(crate) struct Cursor<T> {
+    r: T,
+    pub(crate) position: u64, // bits read since the decoder began
+    pub(crate) point: u64,    // index of the DataPoint being decoded
+}
+
+impl<T> Cursor<T> {
+    pub(crate) fn new(r: T, position: u64) -> Self {
+        Cursor {
+            r,
+            position,
+            point: 0,
+        }
+    }
+
+    pub(crate) fn get_ref(&self) -> &T {
+        &self.r
+    }
+
+    // context describes the current position in the stream while reading `field`
+    pub(crate) fn context(&self, field: FieldKind) -> Context {
+        Context::new(self.position, self.point, field)
+    }
+
+    // corrupt returns the error for a `field` which ends at the current position and holds bits
+    // that an encoder never writes
+    pub(crate) fn corrupt(&self, field: FieldKind, len: u32) -> Error {
+        Error::Corrupt(Context::new(
+            self.position - u64::from(len),
+            self.point,
+            field,
+        ))
+    }
+}
+
+impl<T> Cursor<T>
+where
+    T: Read,
+{
+    pub(crate) fn read_bit(&mut self, field: FieldKind) -> Result<Bit, Error> {
+        let bit = self
+            .r
+            .read_bit()
+            .map_err(|err| Error::at(err, self.context(field)))?;
+        self.position += 1;
+        Ok(bit)
+    }
+
+    pub(crate) fn read_bits(&mut self, num: u32, field: FieldKind) -> Result<u64, Error> {
+        let bits = self
+            .r
+            .read_bits(num)
+            .map_err(|err| Error::at(err, self.context(field)))?;
+        self.position += u64::from(num);
+        Ok(bits)
+    }
+
+    pub(crate) fn peak_bits(&mut self, num: u32, field: FieldKind) -> Result<u64, Error> {
+        self.r
+            .peak_bits(num)
+            .map_err(|err| Error::at(err, self.context(field)))
+    }
+
+    pub(crate) fn skip_bits(&mut self, num: u32, field: FieldKind) -> Result<(), Error> {
+        self.r
+            .skip_bits(num)
+            .map_err(|err| Error::at(err, self.context(field)))?;
+        self.position += u64::from(num);
+        Ok(())
+    }
+}
+
+impl<T> Cursor<T>
+where
+    T: Seek,
+{
+    // seek moves to `position` in the stream, where the DataPoint at index `point` begins
+    pub(crate) fn seek(&mut self, position: u64, point: u64) -> Result<(), Error> {
+        self.r.seek(position)?;
+        self.position = position;
+        self.point = point;
+        Ok(())
+    }
+}
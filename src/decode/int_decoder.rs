@@ -0,0 +1,220 @@
+use std::iter::FusedIterator;
+
+use decode::cursor::Cursor;
+use decode::timestamp::TimestampReader;
+use decode::{Error, FieldKind};
+use encode::int_encoder::VALUE_BUCKET_LENS;
+use header::{Header, ValueCodec};
+use stream::Read;
+use {Bit, IntDataPoint, TimeUnit};
+
+// unzigzag reverses the zigzag encoding applied by `IntEncoder`
+fn unzigzag(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
+/// IntDecoder
+///
+/// IntDecoder is used to decode `IntDataPoint`s written by an `IntEncoder`
+#[derive(Debug)]
+pub struct IntDecoder<T: Read> {
+    timestamps: TimestampReader, // current time and time delta
+    value: i64,                  // current value
+    delta: i64,                  // current value delta
+
+    first: bool, // will next IntDataPoint be the first IntDataPoint decoded
+    done: bool,
+
+    r: Cursor<T>,
+}
+
+impl<T> IntDecoder<T>
+where
+    T: Read,
+{
+    /// new creates a new IntDecoder which will read bytes from r
+    pub fn new(r: T) -> Self {
+        IntDecoder::with_time_unit(r, TimeUnit::Second)
+    }
+
+    /// with_time_unit creates a new IntDecoder which will read bytes from r, which must have been
+    /// written by an `IntEncoder` whose timestamps have a resolution of `unit`
+    pub fn with_time_unit(r: T, unit: TimeUnit) -> Self {
+        IntDecoder {
+            timestamps: TimestampReader::new(unit),
+            value: 0,
+            delta: 0,
+            first: true,
+            done: false,
+            r: Cursor::new(r, 0),
+        }
+    }
+
+    /// with_header creates a new IntDecoder which will read bytes from r, which must begin with
+    /// the `Header` written by `IntEncoder::with_header`. The time unit is taken from the header.
+    pub fn with_header(mut r: T) -> Result<Self, Error> {
+        let header = Header::read(&mut r)?;
+        if header.codec() != ValueCodec::Integer {
+            return Err(Error::UnexpectedCodec(header.codec()));
+        }
+
+        let mut decoder = IntDecoder::with_time_unit(r, header.time_unit());
+        decoder.r.position = u64::from(Header::LEN);
+        Ok(decoder)
+    }
+
+    /// decode returns the next `IntDataPoint` in the stream, see `Decode::next`
+    pub fn decode(&mut self) -> Result<IntDataPoint, Error> {
+        if self.done {
+            return Err(Error::EndOfStream);
+        }
+
+        // the stream may be left in the middle of a DataPoint after an error, so any error ends it
+        let dp = self.read_point();
+        if dp.is_err() {
+            self.done = true;
+        }
+        dp
+    }
+
+    // read_point reads the next IntDataPoint, see `decode`
+    fn read_point(&mut self) -> Result<IntDataPoint, Error> {
+        let time;
+        let value = if self.first {
+            self.first = false;
+            time = self.timestamps.read_first(&mut self.r)?;
+            self.read_first_value()?
+        } else {
+            time = self.timestamps.read_next(&mut self.r)?;
+            self.read_next_value()?
+        };
+        self.r.point += 1;
+
+        Ok(IntDataPoint::new(time, value))
+    }
+
+    fn read_first_value(&mut self) -> Result<i64, Error> {
+        self.value = self.r.read_bits(64, FieldKind::FirstValue)? as i64;
+        Ok(self.value)
+    }
+
+    fn read_next_value(&mut self) -> Result<i64, Error> {
+        let mut control_bits = 0;
+        for _ in 0..4 {
+            let bit = self.r.read_bit(FieldKind::ValueControl)?;
+
+            if bit == Bit::One {
+                control_bits += 1;
+            } else {
+                break;
+            }
+        }
+
+        if control_bits > 0 {
+            let size = VALUE_BUCKET_LENS[control_bits - 1];
+            let dod = self.r.read_bits(size, FieldKind::ValueDeltaOfDelta)?;
+            self.delta = self.delta.wrapping_add(unzigzag(dod));
+        }
+
+        self.value = self.value.wrapping_add(self.delta);
+        Ok(self.value)
+    }
+}
+
+impl<T> Iterator for IntDecoder<T>
+where
+    T: Read,
+{
+    type Item = Result<IntDataPoint, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.decode() {
+            Ok(dp) => Some(Ok(dp)),
+            Err(Error::EndOfStream) => None,
+            // the decoder cannot recover from an error, so it ends the stream after reporting it
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+impl<T> FusedIterator for IntDecoder<T> where T: Read {}
+
+#[cfg(test)]
+mod tests {
+    use super::{unzigzag, IntDecoder};
+    use decode::Error;
+    use encode::int_encoder::IntEncoder;
+    use header::ValueCodec;
+    use stream::{BufferedReader, BufferedWriter};
+    use {Encode, IntDataPoint, StdEncoder, TimeUnit};
+
+    #[test]
+    fn unzigzag_values() {
+        assert_eq!(unzigzag(0), 0);
+        assert_eq!(unzigzag(1), -1);
+        assert_eq!(unzigzag(2), 1);
+        assert_eq!(unzigzag(u64::MAX - 1), i64::MAX);
+        assert_eq!(unzigzag(u64::MAX), i64::MIN);
+    }
+
+    #[test]
+    fn decode_datapoints() {
+        let start = 1482268055;
+        let values = [
+            0,
+            1,
+            1,
+            100,
+            -100,
+            70_000,
+            -5_000_000_000,
+            i64::MAX,
+            i64::MIN,
+            0,
+            42,
+        ];
+        let dps: Vec<IntDataPoint> = values
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| IntDataPoint::new(start + 10 + i as u64 * 10, v))
+            .collect();
+
+        let mut e = IntEncoder::new(start, BufferedWriter::new());
+        for &dp in &dps {
+            e.try_encode(dp).unwrap();
+        }
+
+        let r = BufferedReader::new(e.close());
+        let decoder = IntDecoder::new(r);
+        let actual: Result<Vec<IntDataPoint>, Error> = decoder.collect();
+
+        assert_eq!(actual.unwrap(), dps);
+    }
+
+    #[test]
+    fn decode_with_header() {
+        let start = 1482268055000;
+        let dps: Vec<IntDataPoint> = (1..500)
+            .map(|i| IntDataPoint::new(start + i * 1000 + i % 7, (i * i) as i64))
+            .collect();
+
+        let mut e = IntEncoder::with_header(start, TimeUnit::Millisecond, BufferedWriter::new());
+        for &dp in &dps {
+            e.encode(dp);
+        }
+
+        let r = BufferedReader::new(e.try_close().unwrap());
+        let decoder = IntDecoder::with_header(r).unwrap();
+        let actual: Result<Vec<IntDataPoint>, Error> = decoder.collect();
+
+        assert_eq!(actual.unwrap(), dps);
+
+        // streams of float values are rejected
+        let e = StdEncoder::with_header(start, TimeUnit::Millisecond, BufferedWriter::new());
+        let r = BufferedReader::new(e.close());
+        assert_eq!(
+            IntDecoder::with_header(r).err().unwrap(),
+            Error::UnexpectedCodec(ValueCodec::Gorilla)
+        );
+    }
+}
@@ -1,25 +1,178 @@
-use std::fmt;
+use header::ValueCodec;
+use std::{error, fmt};
 use stream;
+use stream::SliceReader;
 use DataPoint;
 
+/// FieldKind
+///
+/// FieldKind is a field of an encoded stream, which a decoder was reading when it encountered an
+/// error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    /// The `Header` of the stream.
+    Header,
+    /// The starting timestamp of the stream.
+    StartTime,
+    /// The single bit which shows whether the stream holds any `DataPoint`s.
+    FirstControl,
+    /// The delta between the starting timestamp and the time of the first `DataPoint`.
+    FirstDelta,
+    /// The value of the first `DataPoint`.
+    FirstValue,
+    /// The control bits which select the bucket of a delta of delta.
+    TimestampControl,
+    /// A delta of delta of the timestamps.
+    DeltaOfDelta,
+    /// The control bits which select how a value is stored.
+    ValueControl,
+    /// The position of the previous value a value is stored against, in the Chimp128 scheme.
+    PreviousValue,
+    /// The number of leading zeroes of an xor.
+    LeadingZeroes,
+    /// The number of significant bits of an xor.
+    SignificantBits,
+    /// The meaningful bits of the xor of a value with a previous value.
+    Xor,
+    /// A delta of delta of the values of an integer stream.
+    ValueDeltaOfDelta,
+    /// The empty largest bucket which ends the stream.
+    EndMarker,
+}
+
+impl fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            FieldKind::Header => "header",
+            FieldKind::StartTime => "starting timestamp",
+            FieldKind::FirstControl => "first control bit",
+            FieldKind::FirstDelta => "first delta",
+            FieldKind::FirstValue => "first value",
+            FieldKind::TimestampControl => "timestamp control bits",
+            FieldKind::DeltaOfDelta => "delta of delta",
+            FieldKind::ValueControl => "value control bits",
+            FieldKind::PreviousValue => "previous value index",
+            FieldKind::LeadingZeroes => "leading zero count",
+            FieldKind::SignificantBits => "significant bit count",
+            FieldKind::Xor => "xor value",
+            FieldKind::ValueDeltaOfDelta => "value delta of delta",
+            FieldKind::EndMarker => "end marker",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Context
+///
+/// Context describes where in a stream a decoder encountered an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Context {
+    offset: u64,      // bit offset of the read which failed
+    point: u64,       // index of the DataPoint being decoded
+    field: FieldKind, // field being read
+}
+
+impl Context {
+    pub(crate) fn new(offset: u64, point: u64, field: FieldKind) -> Self {
+        Context {
+            offset,
+            point,
+            field,
+        }
+    }
+
+    /// Get the offset, in bits, of the read which failed. It is counted from where the decoder
+    /// began reading, including the `Header` if the decoder read it, so it is the offset from the
+    /// start of the stream unless the reader was moved past part of the stream beforehand.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the index of the `DataPoint` being decoded, from 0 for the first `DataPoint`.
+    pub fn point(&self) -> u64 {
+        self.point
+    }
+
+    /// Get the field being read.
+    pub fn field(&self) -> FieldKind {
+        self.field
+    }
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of point {} at bit {}",
+            self.field, self.point, self.offset
+        )
+    }
+}
+
 /// Error
 ///
 /// Error encapsulates the potential errors that can be encountered when decoding data
 #[derive(Debug, PartialEq)]
 pub enum Error {
+    /// The underlying reader returned an error.
     Stream(stream::Error),
-    InvalidInitialTimestamp,
-    InvalidEndOfStream,
+    /// The stream ended in the middle of a field, before its end marker, which means it was
+    /// truncated.
+    Truncated(Context),
+    /// A field holds bits which an encoder never writes, which means the stream was corrupted.
+    /// Not every corruption can be detected, a flipped bit often decodes to a wrong `DataPoint`
+    /// or leads the decoder to read past the end of the stream instead.
+    Corrupt(Context),
+    /// Every `DataPoint` in the stream has been decoded.
     EndOfStream,
+    /// The stream does not begin with the magic number of a `Header`.
+    BadMagic,
+    /// The `Header` has a format version this crate cannot read.
+    UnsupportedVersion(u8),
+    /// The `Header` records an unknown time unit or codec.
+    InvalidHeader,
+    /// The `Header` records a codec other than the one of the decoder.
+    UnexpectedCodec(ValueCodec),
+}
+
+impl Error {
+    // at converts an error returned by a stream while reading the field described by `context`,
+    // reporting the end of the stream as truncation
+    pub(crate) fn at(err: stream::Error, context: Context) -> Error {
+        match err {
+            stream::Error::EOF => Error::Truncated(context),
+            err => Error::Stream(err),
+        }
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::Stream(ref err) => write!(f, "Stream error: {}", err),
-            Error::InvalidInitialTimestamp => write!(f, "Failed to parse intitial timestamp"),
-            Error::InvalidEndOfStream => write!(f, "Encountered invalid end of steam marker"),
+            Error::Truncated(ref context) => {
+                write!(f, "Stream is truncated, it ended in the {}", context)
+            }
+            Error::Corrupt(ref context) => write!(f, "Stream is corrupt, invalid {}", context),
             Error::EndOfStream => write!(f, "Encountered end of the stream"),
+            Error::BadMagic => write!(f, "Stream does not begin with a header"),
+            Error::UnsupportedVersion(version) => {
+                write!(f, "Unsupported stream format version {}", version)
+            }
+            Error::InvalidHeader => write!(f, "Failed to parse stream header"),
+            Error::UnexpectedCodec(codec) => {
+                write!(f, "Stream values were compressed with {:?}", codec)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Stream(ref err) => Some(err),
+            _ => None,
         }
     }
 }
@@ -34,7 +187,23 @@ impl From<stream::Error> for Error {
 ///
 /// Decode is the trait used to encapsulate decoding `DataPoint`s
 pub trait Decode {
+    /// Decode the next `DataPoint`, or return `Error::EndOfStream` once every `DataPoint` has been
+    /// decoded. A decoder cannot continue after any other error, so every later call returns
+    /// `Error::EndOfStream`.
     fn next(&mut self) -> Result<DataPoint, Error>;
 }
 
+/// decode_all decodes every `DataPoint` in `bytes`, which should have been produced by a
+/// `StdEncoder`
+pub fn decode_all(bytes: &[u8]) -> Result<Vec<DataPoint>, Error> {
+    let r = SliceReader::new(bytes);
+    std_decoder::StdDecoder::new(r).collect()
+}
+
+pub mod chimp_decoder;
+pub mod int_decoder;
 pub mod std_decoder;
+pub mod timestamp_decoder;
+
+mod cursor;
+mod timestamp;
@@ -1,24 +1,29 @@
-use decode::{Decode, Error};
-use encode::std_encoder::{END_MARKER, END_MARKER_LEN};
-use stream::Read;
-use {Bit, DataPoint};
+use std::iter::FusedIterator;
+
+use decode::cursor::Cursor;
+use decode::timestamp::TimestampReader;
+use decode::{Decode, Error, FieldKind};
+use header::{Header, ValueCodec};
+use index::BlockIndex;
+use stream::{Read, Seek};
+use {Bit, DataPoint, TimeUnit};
 
 /// StdDecoder
 ///
 /// StdDecoder is used to decode `DataPoint`s
 #[derive(Debug)]
 pub struct StdDecoder<T: Read> {
-    time: u64,       // current time
-    delta: u64,      // current time delta
-    value_bits: u64, // current float value as bits
+    timestamps: TimestampReader, // current time and time delta
+    value_bits: u64,             // current float value as bits
 
     leading_zeroes: u32,  // leading zeroes
     trailing_zeroes: u32, // trailing zeroes
 
     first: bool, // will next DataPoint be the first DataPoint decoded
     done: bool,
+    error: Option<Error>, // error held back by decode_into to return from the next call
 
-    r: T,
+    r: Cursor<T>,
 }
 
 impl<T> StdDecoder<T>
@@ -27,129 +32,223 @@ where
 {
     /// new creates a new StdDecoder which will read bytes from r
     pub fn new(r: T) -> Self {
+        StdDecoder::with_time_unit(r, TimeUnit::Second)
+    }
+
+    /// with_time_unit creates a new StdDecoder which will read bytes from r, which must have been
+    /// written by a `StdEncoder` whose timestamps have a resolution of `unit`
+    pub fn with_time_unit(r: T, unit: TimeUnit) -> Self {
         StdDecoder {
-            time: 0,
-            delta: 0,
+            timestamps: TimestampReader::new(unit),
             value_bits: 0,
-            leading_zeroes: 0,
-            trailing_zeroes: 0,
+            leading_zeroes: 64, // 64 is an initial sentinel value, as in the encoder
+            trailing_zeroes: 64, // 64 is an initial sentinel value
             first: true,
             done: false,
-            r,
-        }
-    }
-
-    fn read_initial_timestamp(&mut self) -> Result<u64, Error> {
-        self.r
-            .read_bits(64)
-            .map_err(|_| Error::InvalidInitialTimestamp)
-            .map(|time| {
-                self.time = time;
-                time
-            })
-    }
-
-    fn read_first_timestamp(&mut self) -> Result<u64, Error> {
-        self.read_initial_timestamp()?;
-
-        // sanity check to confirm that the stream contains more than just the initial timestamp
-        let control_bit = self.r.peak_bits(1)?;
-        if control_bit == 1 {
-            return self
-                .r
-                .read_bits(END_MARKER_LEN)
-                .map_err(Error::Stream)
-                .and_then(|marker| {
-                    if marker == END_MARKER {
-                        Err(Error::EndOfStream)
-                    } else {
-                        Err(Error::InvalidEndOfStream)
-                    }
-                });
+            error: None,
+            r: Cursor::new(r, 0),
         }
+    }
 
-        // stream contains datapoints so we can throw away the control bit
-        self.r.read_bit()?;
+    /// with_header creates a new StdDecoder which will read bytes from r, which must begin with
+    /// the `Header` written by `StdEncoder::with_header`. The time unit is taken from the header.
+    pub fn with_header(mut r: T) -> Result<Self, Error> {
+        let header = Header::read(&mut r)?;
+        if header.codec() != ValueCodec::Gorilla {
+            return Err(Error::UnexpectedCodec(header.codec()));
+        }
 
-        self.r.read_bits(14).map(|delta| {
-            self.delta = delta;
-            self.time += delta;
-        })?;
+        let mut decoder = StdDecoder::with_time_unit(r, header.time_unit());
+        decoder.r.position = u64::from(Header::LEN);
+        Ok(decoder)
+    }
 
-        Ok(self.time)
+    /// get_ref returns a reference to the underlying reader
+    pub fn get_ref(&self) -> &T {
+        self.r.get_ref()
     }
 
-    fn read_next_timestamp(&mut self) -> Result<u64, Error> {
-        let mut control_bits = 0;
-        for _ in 0..4 {
-            let bit = self.r.read_bit()?;
+    fn read_first_value(&mut self) -> Result<u64, Error> {
+        self.value_bits = self.r.read_bits(64, FieldKind::FirstValue)?;
+        Ok(self.value_bits)
+    }
 
-            if bit == Bit::One {
-                control_bits += 1;
-            } else {
-                break;
-            }
+    fn read_next_value(&mut self) -> Result<u64, Error> {
+        let contol_bit = self.r.read_bit(FieldKind::ValueControl)?;
+
+        if contol_bit == Bit::Zero {
+            return Ok(self.value_bits);
         }
 
-        let size = match control_bits {
-            0 => {
-                self.time += self.delta;
-                return Ok(self.time);
-            }
-            1 => 7,
-            2 => 9,
-            3 => 12,
-            4 => 32,
-            _ => unreachable!(),
-        };
+        let zeroes_bit = self.r.read_bit(FieldKind::ValueControl)?;
 
-        let mut dod = self.r.read_bits(size)?;
+        let new_window = zeroes_bit == Bit::One;
+        if new_window {
+            let leading_zeroes = self.r.read_bits(6, FieldKind::LeadingZeroes)? as u32;
+            let significant_digits = self.r.read_bits(6, FieldKind::SignificantBits)? as u32 + 1;
+            if leading_zeroes + significant_digits > 64 {
+                return Err(self.r.corrupt(FieldKind::SignificantBits, 6));
+            }
 
-        if control_bits == 4 && dod == 0 {
-            // If the control bits are 1111 and delta-of-delta is 0, the stream has ended.
-            return Err(Error::EndOfStream);
+            self.leading_zeroes = leading_zeroes;
+            self.trailing_zeroes = 64 - leading_zeroes - significant_digits;
+        } else if self.leading_zeroes + self.trailing_zeroes >= 64 {
+            // an encoder only reuses a window once it has stored one
+            return Err(self.r.corrupt(FieldKind::ValueControl, 2));
         }
 
-        // need to sign extend negative numbers
-        if dod > (1 << (size - 1)) {
-            let mask = u64::max_value() << size;
-            dod |= mask;
-        }
+        let size = 64 - self.leading_zeroes - self.trailing_zeroes;
+        let bits = self.r.read_bits(size, FieldKind::Xor)?;
 
-        // by performing a wrapping_add we can ensure that negative numbers will be handled correctly
-        self.delta = self.delta.wrapping_add(dod);
-        self.time = self.time.wrapping_add(self.delta);
+        // an encoder stores a value equal to the previous one with a single zero bit, and the
+        // meaningful bits of a new window begin and end with a one
+        if bits == 0 || (new_window && (bits >> (size - 1) == 0 || bits & 1 == 0)) {
+            return Err(self.r.corrupt(FieldKind::Xor, size));
+        }
 
-        Ok(self.time)
+        self.value_bits ^= bits << self.trailing_zeroes;
+        Ok(self.value_bits)
     }
 
-    fn read_first_value(&mut self) -> Result<u64, Error> {
-        self.r.read_bits(64).map_err(Error::Stream).map(|bits| {
-            self.value_bits = bits;
-            self.value_bits
-        })
-    }
+    // read_point reads the next DataPoint, see `Decode::next`
+    fn read_point(&mut self) -> Result<DataPoint, Error> {
+        let time;
+        let value_bits = if self.first {
+            self.first = false;
+            time = self.timestamps.read_first(&mut self.r)?;
+            self.read_first_value()?
+        } else {
+            time = self.timestamps.read_next(&mut self.r)?;
+            self.read_next_value()?
+        };
 
-    fn read_next_value(&mut self) -> Result<u64, Error> {
-        let contol_bit = self.r.read_bit()?;
+        let value = f64::from_bits(value_bits);
+        self.r.point += 1;
 
-        if contol_bit == Bit::Zero {
-            return Ok(self.value_bits);
+        Ok(DataPoint::new(time, value))
+    }
+
+    /// decode_into decodes `DataPoint`s into the columns `times` and `values` until the shorter
+    /// of them is full or the stream ends, and returns the number of `DataPoint`s decoded. Once
+    /// the stream has ended it returns zero. If an error is encountered after some `DataPoint`s
+    /// were decoded, their number is returned and the error is returned by the next call instead.
+    /// The decoder cannot continue after an error, so every call after it returns zero.
+    pub fn decode_into(&mut self, times: &mut [u64], values: &mut [f64]) -> Result<usize, Error> {
+        let len = times.len().min(values.len());
+        if len == 0 {
+            return Ok(0);
+        }
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        if self.done {
+            return Ok(0);
         }
 
-        let zeroes_bit = self.r.read_bit()?;
+        let mut n = 0;
+        if self.first {
+            match Decode::next(self) {
+                Ok(dp) => {
+                    times[0] = dp.get_time();
+                    values[0] = dp.get_value();
+                    n = 1;
+                }
+                Err(Error::EndOfStream) => return Ok(0),
+                Err(err) => return Err(err),
+            }
+        }
 
-        if zeroes_bit == Bit::One {
-            self.leading_zeroes = self.r.read_bits(6).map(|n| n as u32)?;
-            let significant_digits = self.r.read_bits(6).map(|n| (n + 1) as u32)?;
-            self.trailing_zeroes = 64 - self.leading_zeroes - significant_digits;
+        // after the first DataPoint every DataPoint is decoded the same way, so we can skip the
+        // checks `Decode::next` makes for each one
+        while n < len {
+            let dp = self
+                .timestamps
+                .read_next(&mut self.r)
+                .and_then(|time| self.read_next_value().map(|bits| (time, bits)));
+            match dp {
+                Ok((time, value_bits)) => {
+                    times[n] = time;
+                    values[n] = f64::from_bits(value_bits);
+                    self.r.point += 1;
+                    n += 1;
+                }
+                Err(Error::EndOfStream) => {
+                    self.done = true;
+                    break;
+                }
+                Err(err) => {
+                    // the stream may be left in the middle of a DataPoint, so stop decoding
+                    self.done = true;
+                    if n == 0 {
+                        return Err(err);
+                    }
+                    self.error = Some(err);
+                    break;
+                }
+            }
         }
 
-        let size = 64 - self.leading_zeroes - self.trailing_zeroes;
-        self.r.read_bits(size).map_err(Error::Stream).map(|bits| {
-            self.value_bits ^= bits << self.trailing_zeroes;
-            self.value_bits
-        })
+        Ok(n)
+    }
+}
+
+impl<T> StdDecoder<T>
+where
+    T: Read + Seek,
+{
+    /// seek moves the decoder to the first `DataPoint` whose time is at or after `time`, so that
+    /// it is the next one decoded. Rather than decoding the stream from the start, the decoder
+    /// resumes from the closest entry in `index`, which must have been recorded by the
+    /// `StdEncoder` which wrote the stream. If there is no such `DataPoint` the decoder is left at
+    /// the end of the stream.
+    pub fn seek(&mut self, time: u64, index: &BlockIndex) -> Result<(), Error> {
+        match index.find_point(time) {
+            Some((point, entry)) => {
+                self.r.seek(entry.offset(), point)?;
+                self.timestamps.time = entry.time();
+                self.timestamps.delta = entry.delta();
+                self.value_bits = entry.value_bits();
+                self.leading_zeroes = entry.leading_zeroes();
+                self.trailing_zeroes = entry.trailing_zeroes();
+                self.first = false;
+            }
+            None => {
+                self.r.seek(index.start(), 0)?;
+                self.timestamps = TimestampReader::new(self.timestamps.unit);
+                self.value_bits = 0;
+                self.leading_zeroes = 64;
+                self.trailing_zeroes = 64;
+                self.first = true;
+            }
+        }
+        self.done = false;
+        self.error = None;
+
+        // decode forward from the entry, saving the state before each DataPoint so we can return
+        // to it once we find the first one at or after time
+        loop {
+            let (position, point) = (self.r.position, self.r.point);
+            let timestamps = self.timestamps.clone();
+            let value_bits = self.value_bits;
+            let leading_zeroes = self.leading_zeroes;
+            let trailing_zeroes = self.trailing_zeroes;
+            let first = self.first;
+
+            match Decode::next(self) {
+                Ok(dp) if dp.get_time() < time => (),
+                Ok(_) => {
+                    self.r.seek(position, point)?;
+                    self.timestamps = timestamps;
+                    self.value_bits = value_bits;
+                    self.leading_zeroes = leading_zeroes;
+                    self.trailing_zeroes = trailing_zeroes;
+                    self.first = first;
+                    return Ok(());
+                }
+                Err(Error::EndOfStream) => return Ok(()),
+                Err(err) => return Err(err),
+            }
+        }
     }
 }
 
@@ -158,42 +257,46 @@ where
     T: Read,
 {
     fn next(&mut self) -> Result<DataPoint, Error> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
         if self.done {
             return Err(Error::EndOfStream);
         }
 
-        let time;
-        let value_bits = if self.first {
-            self.first = false;
-            time = self.read_first_timestamp().map_err(|err| {
-                if err == Error::EndOfStream {
-                    self.done = true;
-                }
-                err
-            })?;
-            self.read_first_value()?
-        } else {
-            time = self.read_next_timestamp().map_err(|err| {
-                if err == Error::EndOfStream {
-                    self.done = true;
-                }
-                err
-            })?;
-            self.read_next_value()?
-        };
-
-        let value = f64::from_bits(value_bits);
+        // the stream may be left in the middle of a DataPoint after an error, so any error ends it
+        let dp = self.read_point();
+        if dp.is_err() {
+            self.done = true;
+        }
+        dp
+    }
+}
 
-        Ok(DataPoint::new(time, value))
+impl<T> Iterator for StdDecoder<T>
+where
+    T: Read,
+{
+    type Item = Result<DataPoint, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match Decode::next(self) {
+            Ok(dp) => Some(Ok(dp)),
+            Err(Error::EndOfStream) => None,
+            // the decoder cannot recover from an error, so it ends the stream after reporting it
+            Err(err) => Some(Err(err)),
+        }
     }
 }
 
+impl<T> FusedIterator for StdDecoder<T> where T: Read {}
+
 #[cfg(test)]
 mod tests {
     use super::StdDecoder;
-    use decode::Error;
-    use stream::BufferedReader;
-    use {DataPoint, Decode};
+    use decode::{Context, Error, FieldKind};
+    use stream::{BufferedReader, BufferedWriter, IoBitReader, SliceReader, Write};
+    use {DataPoint, Decode, Encode, StdEncoder, TimeUnit};
 
     #[test]
     fn create_new_decoder() {
@@ -201,7 +304,10 @@ mod tests {
         let r = BufferedReader::new(bytes.into_boxed_slice());
         let mut decoder = StdDecoder::new(r);
 
-        assert_eq!(decoder.next().err().unwrap(), Error::EndOfStream);
+        assert_eq!(
+            Decode::next(&mut decoder).err().unwrap(),
+            Error::EndOfStream
+        );
     }
 
     #[test]
@@ -215,8 +321,11 @@ mod tests {
 
         let expected_datapoint = DataPoint::new(1482268055 + 10, 1.24);
 
-        assert_eq!(decoder.next().unwrap(), expected_datapoint);
-        assert_eq!(decoder.next().err().unwrap(), Error::EndOfStream);
+        assert_eq!(Decode::next(&mut decoder).unwrap(), expected_datapoint);
+        assert_eq!(
+            Decode::next(&mut decoder).err().unwrap(),
+            Error::EndOfStream
+        );
     }
 
     #[test]
@@ -235,11 +344,330 @@ mod tests {
         let fourth_expected_datapoint = DataPoint::new(1482268055 + 44, -7.41);
         let fifth_expected_datapoint = DataPoint::new(1482268055 + 52, 103.50);
 
-        assert_eq!(decoder.next().unwrap(), first_expected_datapoint);
-        assert_eq!(decoder.next().unwrap(), second_expected_datapoint);
-        assert_eq!(decoder.next().unwrap(), third_expected_datapoint);
-        assert_eq!(decoder.next().unwrap(), fourth_expected_datapoint);
-        assert_eq!(decoder.next().unwrap(), fifth_expected_datapoint);
-        assert_eq!(decoder.next().err().unwrap(), Error::EndOfStream);
+        assert_eq!(
+            Decode::next(&mut decoder).unwrap(),
+            first_expected_datapoint
+        );
+        assert_eq!(
+            Decode::next(&mut decoder).unwrap(),
+            second_expected_datapoint
+        );
+        assert_eq!(
+            Decode::next(&mut decoder).unwrap(),
+            third_expected_datapoint
+        );
+        assert_eq!(
+            Decode::next(&mut decoder).unwrap(),
+            fourth_expected_datapoint
+        );
+        assert_eq!(
+            Decode::next(&mut decoder).unwrap(),
+            fifth_expected_datapoint
+        );
+        assert_eq!(
+            Decode::next(&mut decoder).err().unwrap(),
+            Error::EndOfStream
+        );
+    }
+
+    #[test]
+    fn iterate_datapoints() {
+        let bytes = vec![
+            0, 0, 0, 0, 88, 89, 157, 151, 0, 20, 127, 231, 174, 20, 122, 225, 71, 174, 204, 207,
+            30, 71, 145, 228, 121, 30, 96, 88, 61, 255, 253, 91, 214, 245, 189, 111, 91, 3, 232, 1,
+            245, 97, 88, 86, 21, 133, 55, 202, 1, 17, 15, 92, 40, 245, 194, 151, 128, 0, 0, 0, 0,
+        ];
+        let r = BufferedReader::new(bytes.into_boxed_slice());
+        let decoder = StdDecoder::new(r);
+
+        let times: Vec<u64> = decoder
+            .map(|dp| dp.unwrap().get_time() - 1482268055)
+            .take_while(|&t| t < 40)
+            .collect();
+
+        assert_eq!(times, vec![10, 20, 32]);
+    }
+
+    #[test]
+    fn iterate_stops_after_error() {
+        // the stream is truncated in the middle of the first value
+        let bytes = vec![0, 0, 0, 0, 88, 89, 157, 151, 0, 20, 127, 231, 174];
+        let r = BufferedReader::new(bytes.into_boxed_slice());
+        let mut decoder = StdDecoder::new(r);
+
+        let err = Iterator::next(&mut decoder).unwrap().err().unwrap();
+        assert_eq!(
+            err,
+            Error::Truncated(Context::new(79, 0, FieldKind::FirstValue))
+        );
+        assert_eq!(
+            err.to_string(),
+            "Stream is truncated, it ended in the first value of point 0 at bit 79"
+        );
+        assert!(Iterator::next(&mut decoder).is_none());
+    }
+
+    #[test]
+    fn next_ends_after_error() {
+        // the stream is truncated in the middle of the first value
+        let bytes = vec![0, 0, 0, 0, 88, 89, 157, 151, 0, 20, 127, 231, 174];
+        let mut decoder = StdDecoder::new(SliceReader::new(&bytes));
+
+        assert_eq!(
+            Decode::next(&mut decoder).err().unwrap(),
+            Error::Truncated(Context::new(79, 0, FieldKind::FirstValue))
+        );
+        assert_eq!(
+            Decode::next(&mut decoder).err().unwrap(),
+            Error::EndOfStream
+        );
+
+        let mut times = [0; 4];
+        let mut values = [0.0; 4];
+        assert_eq!(decoder.decode_into(&mut times, &mut values), Ok(0));
+    }
+
+    #[test]
+    fn detect_truncated_stream() {
+        let start = 1482268055;
+        let dps: Vec<DataPoint> = (0..100)
+            .map(|i| DataPoint::new(start + 10 + i * 10 + i % 3, (i % 7) as f64 * 0.1))
+            .collect();
+
+        let mut e = StdEncoder::new(start, BufferedWriter::new());
+        for &dp in &dps {
+            e.encode(dp);
+        }
+        let bytes = e.close();
+
+        // the end marker is the last 36 bits of the stream, any cut before it is a truncation
+        let end = bytes.len() * 8 - 36;
+        for cut in 0..end / 8 {
+            let mut decoded = Vec::new();
+            let mut decoder = StdDecoder::new(SliceReader::new(&bytes[..cut]));
+            let err = loop {
+                match Iterator::next(&mut decoder) {
+                    Some(Ok(dp)) => decoded.push(dp),
+                    Some(Err(err)) => break err,
+                    None => panic!("decoded a stream truncated to {} bytes", cut),
+                }
+            };
+
+            assert_eq!(decoded[..], dps[..decoded.len()]);
+            match err {
+                Error::Truncated(context) => {
+                    assert_eq!(context.point(), decoded.len() as u64);
+                    assert!(context.offset() <= cut as u64 * 8);
+                }
+                err => panic!("unexpected error {:?}", err),
+            }
+        }
+    }
+
+    #[test]
+    fn detect_corrupt_stream() {
+        // write the starting timestamp and a first DataPoint 10 seconds after it
+        let start = |w: &mut BufferedWriter| {
+            w.write_bits(1482268055, 64);
+            w.write_bits(0, 1);
+            w.write_bits(10, 14);
+            w.write_bits(1.5f64.to_bits(), 64);
+        };
+
+        // an end marker whose bucket is not empty
+        let mut w = BufferedWriter::new();
+        w.write_bits(1482268055, 64);
+        w.write_bits(0b1111, 4);
+        w.write_bits(1, 32);
+        let mut decoder = StdDecoder::new(BufferedReader::new(w.close()));
+        assert_eq!(
+            Decode::next(&mut decoder).err().unwrap(),
+            Error::Corrupt(Context::new(68, 0, FieldKind::EndMarker))
+        );
+
+        // a window whose leading zeroes and significant bits add up to more than 64
+        let mut w = BufferedWriter::new();
+        start(&mut w);
+        w.write_bits(0, 1);
+        w.write_bits(0b11, 2);
+        w.write_bits(63, 6);
+        w.write_bits(10, 6);
+        let mut decoder = StdDecoder::new(BufferedReader::new(w.close()));
+        assert!(Decode::next(&mut decoder).is_ok());
+        assert_eq!(
+            Decode::next(&mut decoder).err().unwrap(),
+            Error::Corrupt(Context::new(152, 1, FieldKind::SignificantBits))
+        );
+
+        // a window reused before any was stored
+        let mut w = BufferedWriter::new();
+        start(&mut w);
+        w.write_bits(0, 1);
+        w.write_bits(0b10, 2);
+        let mut decoder = StdDecoder::new(BufferedReader::new(w.close()));
+        assert!(Decode::next(&mut decoder).is_ok());
+        assert_eq!(
+            Decode::next(&mut decoder).err().unwrap(),
+            Error::Corrupt(Context::new(144, 1, FieldKind::ValueControl))
+        );
+
+        // a new window whose meaningful bits do not end with a one
+        let mut w = BufferedWriter::new();
+        start(&mut w);
+        w.write_bits(0, 1);
+        w.write_bits(0b11, 2);
+        w.write_bits(2, 6);
+        w.write_bits(3, 6);
+        w.write_bits(0b1010, 4);
+        let mut decoder = StdDecoder::new(BufferedReader::new(w.close()));
+        assert!(Decode::next(&mut decoder).is_ok());
+        assert_eq!(
+            Decode::next(&mut decoder).err().unwrap(),
+            Error::Corrupt(Context::new(158, 1, FieldKind::Xor))
+        );
+
+        // a delta of delta which moves the time before the previous one
+        let mut w = BufferedWriter::new();
+        start(&mut w);
+        w.write_bits(0, 2);
+        w.write_bits(0b110, 3);
+        w.write_bits(-100i64 as u64, 9);
+        let mut decoder = StdDecoder::new(BufferedReader::new(w.close()));
+        assert!(Decode::next(&mut decoder).is_ok());
+        assert!(Decode::next(&mut decoder).is_ok());
+        assert_eq!(
+            Decode::next(&mut decoder).err().unwrap(),
+            Error::Corrupt(Context::new(148, 2, FieldKind::DeltaOfDelta))
+        );
+    }
+
+    #[test]
+    fn decode_from_io_reader() {
+        let bytes = vec![
+            0, 0, 0, 0, 88, 89, 157, 151, 0, 20, 127, 231, 174, 20, 122, 225, 71, 174, 204, 207,
+            30, 71, 145, 228, 121, 30, 96, 88, 61, 255, 253, 91, 214, 245, 189, 111, 91, 3, 232, 1,
+            245, 97, 88, 86, 21, 133, 55, 202, 1, 17, 15, 92, 40, 245, 194, 151, 128, 0, 0, 0, 0,
+        ];
+        let r = IoBitReader::with_capacity(16, &bytes[..]);
+        let decoder = StdDecoder::new(r);
+
+        let r = BufferedReader::new(bytes.clone().into_boxed_slice());
+        let expected_decoder = StdDecoder::new(r);
+
+        let datapoints: Result<Vec<DataPoint>, Error> = decoder.collect();
+        let expected_datapoints: Result<Vec<DataPoint>, Error> = expected_decoder.collect();
+
+        assert_eq!(datapoints.unwrap(), expected_datapoints.unwrap());
+    }
+
+    #[test]
+    fn seek_with_index() {
+        let start = 1482268055;
+        let dps: Vec<DataPoint> = (0..1000)
+            .map(|i| DataPoint::new(start + 10 + i * 10 + i % 3, (i % 17) as f64 * 1.5))
+            .collect();
+
+        for &header in &[false, true] {
+            let w = BufferedWriter::new();
+            let mut e = if header {
+                StdEncoder::with_header(start, TimeUnit::Second, w)
+            } else {
+                StdEncoder::new(start, w)
+            };
+            e.enable_index(64);
+            for &dp in &dps {
+                e.encode(dp);
+            }
+
+            let index = e.index().unwrap().clone();
+            assert_eq!(index.entries().len(), 1000 / 64);
+
+            let bytes = e.close();
+            for &time in &[
+                0,
+                start + 10,
+                start + 15,
+                start + 640,
+                start + 9000,
+                start + 20000,
+            ] {
+                let r = SliceReader::new(&bytes);
+                let mut decoder = if header {
+                    StdDecoder::with_header(r).unwrap()
+                } else {
+                    StdDecoder::new(r)
+                };
+
+                decoder.seek(time, &index).unwrap();
+                let actual: Result<Vec<DataPoint>, Error> = decoder.collect();
+                let expected: Vec<DataPoint> = dps
+                    .iter()
+                    .cloned()
+                    .filter(|dp| dp.get_time() >= time)
+                    .collect();
+
+                assert_eq!(actual.unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn decode_into_columns() {
+        let start = 1482268055;
+        let dps: Vec<DataPoint> = (0..1000)
+            .map(|i| DataPoint::new(start + 10 + i * 10 + i % 3, (i % 17) as f64 * 1.5))
+            .collect();
+
+        let mut e = StdEncoder::new(start, BufferedWriter::new());
+        for &dp in &dps {
+            e.encode(dp);
+        }
+        let bytes = e.close();
+
+        for &(times_len, values_len) in &[(1, 1), (7, 7), (1024, 1024), (64, 100), (100, 64)] {
+            let mut decoder = StdDecoder::new(SliceReader::new(&bytes));
+            let mut times = vec![0; times_len];
+            let mut values = vec![0.0; values_len];
+
+            let mut actual = Vec::new();
+            loop {
+                let n = decoder.decode_into(&mut times, &mut values).unwrap();
+                if n == 0 {
+                    break;
+                }
+
+                assert!(n == times_len.min(values_len) || actual.len() + n == dps.len());
+                for i in 0..n {
+                    actual.push(DataPoint::new(times[i], values[i]));
+                }
+            }
+
+            assert_eq!(actual, dps);
+            assert_eq!(decoder.decode_into(&mut times, &mut values).unwrap(), 0);
+        }
+
+        // an empty stream fills nothing
+        let bytes = StdEncoder::new(start, BufferedWriter::new()).close();
+        let mut decoder = StdDecoder::new(SliceReader::new(&bytes));
+        assert_eq!(decoder.decode_into(&mut [0; 4], &mut [0.0; 4]).unwrap(), 0);
+
+        // the DataPoints decoded before an error are returned, then the error
+        let mut e = StdEncoder::new(start, BufferedWriter::new());
+        for &dp in &dps {
+            e.encode(dp);
+        }
+        let bytes = e.close();
+        let mut decoder = StdDecoder::new(SliceReader::new(&bytes[..bytes.len() / 2]));
+        let (mut times, mut values) = (vec![0; 1024], vec![0.0; 1024]);
+        let n = decoder.decode_into(&mut times, &mut values).unwrap();
+        assert!(n > 0 && n < dps.len());
+        for i in 0..n {
+            assert_eq!(DataPoint::new(times[i], values[i]), dps[i]);
+        }
+        match decoder.decode_into(&mut times, &mut values) {
+            Err(Error::Truncated(context)) => assert_eq!(context.point(), n as u64),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(decoder.decode_into(&mut times, &mut values).unwrap(), 0);
     }
 }
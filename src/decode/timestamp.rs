@@ -0,0 +1,107 @@
+use decode::cursor::Cursor;
+use decode::{Error, FieldKind};
+use stream::Read;
+use {Bit, TimeUnit};
+
+// TimestampReader decodes the timestamps written by `TimestampWriter`. It is shared by the
+// decoders, which only differ in how they decode values.
+#[derive(Debug, Clone)]
+pub(crate) struct TimestampReader {
+    pub(crate) time: u64,      // current time
+    pub(crate) delta: u64,     // current time delta
+    pub(crate) unit: TimeUnit, // resolution of the timestamps
+}
+
+impl TimestampReader {
+    pub(crate) fn new(unit: TimeUnit) -> Self {
+        TimestampReader {
+            time: 0,
+            delta: 0,
+            unit,
+        }
+    }
+
+    fn read_initial<R: Read>(&mut self, r: &mut Cursor<R>) -> Result<u64, Error> {
+        self.time = r.read_bits(64, FieldKind::StartTime)?;
+        Ok(self.time)
+    }
+
+    pub(crate) fn read_first<R: Read>(&mut self, r: &mut Cursor<R>) -> Result<u64, Error> {
+        self.read_initial(r)?;
+
+        // sanity check to confirm that the stream contains more than just the initial timestamp
+        let control_bit = r.peak_bits(1, FieldKind::FirstControl)?;
+        if control_bit == 1 {
+            // the end marker is the control bits 1111 followed by an empty largest bucket
+            let control_bits = r.read_bits(4, FieldKind::TimestampControl)?;
+            let size = self.unit.bucket_lens()[3];
+            let dod = r.read_bits(size, FieldKind::EndMarker)?;
+
+            if control_bits == 0b1111 && dod == 0 {
+                return Err(Error::EndOfStream);
+            }
+
+            return Err(r.corrupt(FieldKind::EndMarker, size));
+        }
+
+        // stream contains datapoints so we can throw away the control bit
+        r.read_bit(FieldKind::FirstControl)?;
+
+        let size = self.unit.first_delta_len();
+        self.delta = r.read_bits(size, FieldKind::FirstDelta)?;
+        self.time = self
+            .time
+            .checked_add(self.delta)
+            .ok_or_else(|| r.corrupt(FieldKind::FirstDelta, size))?;
+
+        Ok(self.time)
+    }
+
+    pub(crate) fn read_next<R: Read>(&mut self, r: &mut Cursor<R>) -> Result<u64, Error> {
+        let mut control_bits = 0;
+        for _ in 0..4 {
+            let bit = r.read_bit(FieldKind::TimestampControl)?;
+
+            if bit == Bit::One {
+                control_bits += 1;
+            } else {
+                break;
+            }
+        }
+
+        if control_bits == 0 {
+            self.time = self
+                .time
+                .checked_add(self.delta)
+                .ok_or_else(|| r.corrupt(FieldKind::TimestampControl, 1))?;
+            return Ok(self.time);
+        }
+
+        let size = self.unit.bucket_lens()[control_bits - 1];
+
+        let mut dod = r.read_bits(size, FieldKind::DeltaOfDelta)?;
+
+        if control_bits == 4 && dod == 0 {
+            // If the control bits are 1111 and delta-of-delta is 0, the stream has ended.
+            return Err(Error::EndOfStream);
+        }
+
+        // need to sign extend negative numbers, unless the bucket already holds all 64 bits
+        if size < 64 && dod > (1 << (size - 1)) {
+            let mask = u64::MAX << size;
+            dod |= mask;
+        }
+
+        // by performing a wrapping_add we can ensure that negative numbers will be handled
+        // correctly, however an encoder never writes a time before the previous one so the time
+        // itself must not wrap
+        let delta = self.delta.wrapping_add(dod);
+        self.time = self
+            .time
+            .checked_add(delta)
+            .ok_or_else(|| r.corrupt(FieldKind::DeltaOfDelta, size))?;
+        self.delta = delta;
+
+        Ok(self.time)
+    }
+}
@@ -0,0 +1,224 @@
+use std::iter::FusedIterator;
+
+use decode::cursor::Cursor;
+use decode::timestamp::TimestampReader;
+use decode::{Error, FieldKind};
+use header::{Header, ValueCodec};
+use stream::Read;
+use {Bit, TimeUnit};
+
+/// TimestampDecoder
+///
+/// TimestampDecoder is used to decode only the timestamps of a stream written by a `StdEncoder`.
+/// It reads just enough of each value to know how long it is and skips the rest, which makes it
+/// faster than a `StdDecoder` for queries which do not need the values, such as counting the
+/// `DataPoint`s in a block or looking for gaps between them.
+#[derive(Debug)]
+pub struct TimestampDecoder<T: Read> {
+    timestamps: TimestampReader, // current time and time delta
+
+    // the number of leading and trailing zeroes in the last xor which stored them, which
+    // determine the length of the xors which do not
+    leading_zeroes: u32,
+    trailing_zeroes: u32,
+
+    first: bool, // will next timestamp be the first timestamp decoded
+    done: bool,
+
+    r: Cursor<T>,
+}
+
+impl<T> TimestampDecoder<T>
+where
+    T: Read,
+{
+    /// new creates a new TimestampDecoder which will read bytes from r
+    pub fn new(r: T) -> Self {
+        TimestampDecoder::with_time_unit(r, TimeUnit::Second)
+    }
+
+    /// with_time_unit creates a new TimestampDecoder which will read bytes from r, which must have
+    /// been written by a `StdEncoder` whose timestamps have a resolution of `unit`
+    pub fn with_time_unit(r: T, unit: TimeUnit) -> Self {
+        TimestampDecoder {
+            timestamps: TimestampReader::new(unit),
+            leading_zeroes: 64, // 64 is an initial sentinel value, as in the encoder
+            trailing_zeroes: 64, // 64 is an initial sentinel value
+            first: true,
+            done: false,
+            r: Cursor::new(r, 0),
+        }
+    }
+
+    /// with_header creates a new TimestampDecoder which will read bytes from r, which must begin
+    /// with the `Header` written by `StdEncoder::with_header`. The time unit is taken from the
+    /// header.
+    pub fn with_header(mut r: T) -> Result<Self, Error> {
+        let header = Header::read(&mut r)?;
+        if header.codec() != ValueCodec::Gorilla {
+            return Err(Error::UnexpectedCodec(header.codec()));
+        }
+
+        let mut decoder = TimestampDecoder::with_time_unit(r, header.time_unit());
+        decoder.r.position = u64::from(Header::LEN);
+        Ok(decoder)
+    }
+
+    /// next_time returns the time of the next `DataPoint` in the stream, or `Error::EndOfStream`
+    /// once every `DataPoint` has been decoded
+    pub fn next_time(&mut self) -> Result<u64, Error> {
+        if self.done {
+            return Err(Error::EndOfStream);
+        }
+
+        // the stream may be left in the middle of a DataPoint after an error, so any error ends it
+        let time = self.read_time();
+        if time.is_err() {
+            self.done = true;
+        }
+        time
+    }
+
+    // read_time reads the time of the next DataPoint, see `next_time`
+    fn read_time(&mut self) -> Result<u64, Error> {
+        let first = self.first;
+        self.first = false;
+
+        let time = if first {
+            self.timestamps.read_first(&mut self.r)
+        } else {
+            self.timestamps.read_next(&mut self.r)
+        };
+
+        let time = time?;
+
+        if first {
+            // the first value is stored exactly
+            self.r.skip_bits(64, FieldKind::FirstValue)?;
+        } else {
+            self.skip_next_value()?;
+        }
+        self.r.point += 1;
+
+        Ok(time)
+    }
+
+    /// count returns the number of `DataPoint`s left in the stream. Unlike `Iterator::count` it
+    /// returns the first error encountered instead of counting it.
+    pub fn count(mut self) -> Result<u64, Error> {
+        let mut count = 0;
+        loop {
+            match self.next_time() {
+                Ok(_) => count += 1,
+                Err(Error::EndOfStream) => return Ok(count),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    // skip_next_value skips past the value which follows a timestamp, see
+    // `StdDecoder::read_next_value` for the layout it skips
+    fn skip_next_value(&mut self) -> Result<(), Error> {
+        if self.r.read_bit(FieldKind::ValueControl)? == Bit::Zero {
+            return Ok(());
+        }
+
+        if self.r.read_bit(FieldKind::ValueControl)? == Bit::One {
+            let leading_zeroes = self.r.read_bits(6, FieldKind::LeadingZeroes)? as u32;
+            let significant_digits = self.r.read_bits(6, FieldKind::SignificantBits)? as u32 + 1;
+            if leading_zeroes + significant_digits > 64 {
+                return Err(self.r.corrupt(FieldKind::SignificantBits, 6));
+            }
+
+            self.leading_zeroes = leading_zeroes;
+            self.trailing_zeroes = 64 - leading_zeroes - significant_digits;
+        } else if self.leading_zeroes + self.trailing_zeroes >= 64 {
+            return Err(self.r.corrupt(FieldKind::ValueControl, 2));
+        }
+
+        let size = 64 - self.leading_zeroes - self.trailing_zeroes;
+        self.r.skip_bits(size, FieldKind::Xor)?;
+
+        Ok(())
+    }
+}
+
+impl<T> Iterator for TimestampDecoder<T>
+where
+    T: Read,
+{
+    type Item = Result<u64, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_time() {
+            Ok(time) => Some(Ok(time)),
+            Err(Error::EndOfStream) => None,
+            // the decoder cannot recover from an error, so it ends the stream after reporting it
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+impl<T> FusedIterator for TimestampDecoder<T> where T: Read {}
+
+#[cfg(test)]
+mod tests {
+    use super::TimestampDecoder;
+    use decode::Error;
+    use stream::{BufferedReader, BufferedWriter};
+    use {DataPoint, Encode, StdDecoder, StdEncoder, TimeUnit};
+
+    fn encode(header: bool) -> Box<[u8]> {
+        let start = 1482268055;
+        let w = BufferedWriter::new();
+        let mut e = if header {
+            StdEncoder::with_header(start, TimeUnit::Second, w)
+        } else {
+            StdEncoder::new(start, w)
+        };
+
+        for i in 0..500u64 {
+            let value = match i % 5 {
+                0 => 1.0,
+                1 => 1.0,
+                2 => -(i as f64) / 3.0,
+                3 => 1e300,
+                _ => i as f64,
+            };
+            e.encode(DataPoint::new(start + 10 + i * 10 + i % 7, value));
+        }
+
+        e.close()
+    }
+
+    #[test]
+    fn decode_timestamps() {
+        let bytes = encode(false);
+
+        let expected: Result<Vec<u64>, Error> = StdDecoder::new(BufferedReader::new(bytes.clone()))
+            .map(|dp| dp.map(|dp| dp.get_time()))
+            .collect();
+        let actual: Result<Vec<u64>, Error> =
+            TimestampDecoder::new(BufferedReader::new(bytes)).collect();
+
+        assert_eq!(actual.unwrap(), expected.unwrap());
+    }
+
+    #[test]
+    fn count_datapoints() {
+        let r = BufferedReader::new(encode(true));
+        let decoder = TimestampDecoder::with_header(r).unwrap();
+        assert_eq!(decoder.count().unwrap(), 500);
+
+        let r = BufferedReader::new(StdEncoder::new(0, BufferedWriter::new()).close());
+        assert_eq!(TimestampDecoder::new(r).count().unwrap(), 0);
+
+        // a truncated stream returns an error rather than a count
+        let bytes = encode(false);
+        let r = BufferedReader::new(bytes[..bytes.len() / 2].to_vec().into_boxed_slice());
+        match TimestampDecoder::new(r).count().err().unwrap() {
+            Error::Truncated(context) => assert!(context.point() > 0),
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+}
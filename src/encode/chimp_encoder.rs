@@ -0,0 +1,372 @@
+use encode::timestamp::TimestampWriter;
+use encode::{Encode, Error};
+use header::{Header, ValueCodec};
+use stream;
+use stream::Write;
+use {DataPoint, TimeUnit};
+
+// The Chimp scheme is described in "Chimp: Efficient Lossless Floating Point Compression for
+// Time Series Databases" by Liakos, Papakonstantinopoulou and Kotidis. Like the Gorilla scheme it
+// stores the xor of each value with a previous value, but it uses a two bit flag to pick one of
+// four cases:
+//
+//   00 - the xor is zero
+//   01 - the xor has many trailing zeroes, so store the number of leading zeroes and significant
+//        bits followed by the significant bits
+//   10 - the xor has the same number of leading zeroes as the last one stored, so store every
+//        bit after the leading zeroes
+//   11 - store the number of leading zeroes followed by every bit after them
+//
+// The number of leading zeroes is rounded down to one of the eight values in LEADING_ZEROES so
+// that it can be stored in three bits.
+
+/// LEADING_ZEROES are the numbers of leading zeroes which can be stored by the Chimp scheme, the
+/// three bits written for the leading zeroes of an xor are an index into this array
+pub(crate) const LEADING_ZEROES: [u32; 8] = [0, 8, 12, 16, 18, 20, 22, 24];
+
+/// PREVIOUS_VALUES is the number of previous values that the Chimp128 scheme can refer to
+pub(crate) const PREVIOUS_VALUES: usize = 128;
+
+// PREVIOUS_VALUES_LOG2 is the number of bits needed to store an index into the previous values
+const PREVIOUS_VALUES_LOG2: u32 = 7;
+
+// THRESHOLD is the number of trailing zeroes an xor must have before it is cheaper to store its
+// significant bits rather than every bit after its leading zeroes
+const THRESHOLD: u32 = 6;
+
+// THRESHOLD_128 is the number of trailing zeroes an xor with one of the previous values must have
+// to be worth storing instead of the xor with the immediately previous value
+const THRESHOLD_128: u32 = THRESHOLD + PREVIOUS_VALUES_LOG2;
+
+// INDICES_LEN is the number of entries in the table which maps the least significant bits of a
+// value to the last index at which they were seen
+const INDICES_LEN: usize = 1 << (THRESHOLD_128 + 1);
+
+// leading_zeroes_code returns the index in LEADING_ZEROES of the largest value which does not
+// exceed `leading_zeroes`
+fn leading_zeroes_code(leading_zeroes: u32) -> u64 {
+    LEADING_ZEROES
+        .iter()
+        .rposition(|&lz| lz <= leading_zeroes)
+        .unwrap_or(0) as u64
+}
+
+// Window holds the previous values the Chimp128 scheme can refer to
+#[derive(Debug)]
+struct Window {
+    values: [u64; PREVIOUS_VALUES], // ring buffer of the previous values
+    indices: Box<[usize]>,          // last index of each combination of least significant bits
+    current: usize,                 // position of the previous value in values
+    index: usize,                   // number of values written after the first
+}
+
+impl Window {
+    fn new() -> Self {
+        Window {
+            values: [0; PREVIOUS_VALUES],
+            indices: vec![0; INDICES_LEN].into_boxed_slice(),
+            current: 0,
+            index: 0,
+        }
+    }
+
+    // find returns the position in values of the previous value to xor `value` with
+    fn find(&self, value: u64) -> usize {
+        let candidate = self.indices[value as usize % INDICES_LEN];
+
+        if self.index - candidate < PREVIOUS_VALUES {
+            let position = candidate % PREVIOUS_VALUES;
+            let xor = value ^ self.values[position];
+            if xor == 0 || xor.trailing_zeros() > THRESHOLD_128 {
+                return position;
+            }
+        }
+
+        self.current
+    }
+
+    fn push(&mut self, value: u64) {
+        self.current = (self.current + 1) % PREVIOUS_VALUES;
+        self.values[self.current] = value;
+        self.index += 1;
+        self.indices[value as usize % INDICES_LEN] = self.index;
+    }
+}
+
+/// ChimpEncoder
+///
+/// ChimpEncoder is used to encode `DataPoint`s using the Chimp scheme for values, or the Chimp128
+/// scheme if it is created with `new_128`. Timestamps are encoded the same way as `StdEncoder`.
+#[derive(Debug)]
+pub struct ChimpEncoder<T: Write> {
+    timestamps: TimestampWriter, // current time and time delta
+    value_bits: u64,             // current float value as bits
+
+    // number of leading zeroes stored for the last xor written with a 11 flag, or 65 if the
+    // next xor must store its leading zeroes
+    leading_zeroes: u32,
+
+    window: Option<Window>, // previous values, only used by the Chimp128 scheme
+
+    first: bool, // will next DataPoint be the first DataPoint encoded
+
+    w: T,
+}
+
+impl<T> ChimpEncoder<T>
+where
+    T: Write,
+{
+    /// new creates a new ChimpEncoder using the Chimp scheme whose starting timestamp is `start`
+    /// and writes its encoded bytes to `w`
+    pub fn new(start: u64, w: T) -> Self {
+        ChimpEncoder::with_time_unit(start, TimeUnit::Second, w)
+    }
+
+    /// new_128 creates a new ChimpEncoder like `new`, but using the Chimp128 scheme
+    pub fn new_128(start: u64, w: T) -> Self {
+        ChimpEncoder::with_time_unit_128(start, TimeUnit::Second, w)
+    }
+
+    /// with_time_unit creates a new ChimpEncoder using the Chimp scheme for timestamps with a
+    /// resolution of `unit`, whose starting timestamp is `start` and which writes its encoded
+    /// bytes to `w`
+    pub fn with_time_unit(start: u64, unit: TimeUnit, w: T) -> Self {
+        ChimpEncoder::create(start, unit, false, w)
+    }
+
+    /// with_time_unit_128 creates a new ChimpEncoder like `with_time_unit`, but using the
+    /// Chimp128 scheme
+    pub fn with_time_unit_128(start: u64, unit: TimeUnit, w: T) -> Self {
+        ChimpEncoder::create(start, unit, true, w)
+    }
+
+    /// with_header creates a new ChimpEncoder like `with_time_unit`, but first writes a `Header`
+    /// recording `unit` so the stream can be decoded by `ChimpDecoder::with_header`
+    pub fn with_header(start: u64, unit: TimeUnit, mut w: T) -> Self {
+        // writers which can fail remember their first error and return it from `try_close`
+        let _ = Header::new(unit, ValueCodec::Chimp).write(&mut w);
+        ChimpEncoder::create(start, unit, false, w)
+    }
+
+    /// with_header_128 creates a new ChimpEncoder like `with_header`, but using the Chimp128
+    /// scheme
+    pub fn with_header_128(start: u64, unit: TimeUnit, mut w: T) -> Self {
+        // writers which can fail remember their first error and return it from `try_close`
+        let _ = Header::new(unit, ValueCodec::Chimp128).write(&mut w);
+        ChimpEncoder::create(start, unit, true, w)
+    }
+
+    fn create(start: u64, unit: TimeUnit, window: bool, w: T) -> Self {
+        let mut e = ChimpEncoder {
+            timestamps: TimestampWriter::new(start, unit),
+            value_bits: 0,
+            leading_zeroes: 65, // 65 is an initial sentinel value
+            window: if window { Some(Window::new()) } else { None },
+            first: true,
+            w,
+        };
+
+        // write timestamp header
+        let _ = e.timestamps.write_start(&mut e.w);
+
+        e
+    }
+
+    fn write(&mut self, dp: DataPoint) -> Result<(), stream::Error> {
+        let value_bits = dp.value.to_bits();
+
+        if self.first {
+            self.timestamps.write_first(&mut self.w, dp.time)?;
+
+            // store the first value exactly
+            self.w.try_write_bits(value_bits, 64)?;
+            self.value_bits = value_bits;
+            if let Some(ref mut window) = self.window {
+                window.values[0] = value_bits;
+            }
+
+            self.first = false;
+            return Ok(());
+        }
+
+        self.timestamps.write_next(&mut self.w, dp.time)?;
+        match self.window.take() {
+            Some(mut window) => {
+                let result = self.write_next_value_128(&mut window, value_bits);
+                self.window = Some(window);
+                result
+            }
+            None => self.write_next_value(value_bits),
+        }
+    }
+
+    fn write_next_value(&mut self, value_bits: u64) -> Result<(), stream::Error> {
+        let xor = value_bits ^ self.value_bits;
+        self.value_bits = value_bits;
+
+        if xor == 0 {
+            self.w.try_write_bits(0b00, 2)?;
+            self.leading_zeroes = 65;
+            return Ok(());
+        }
+
+        let code = leading_zeroes_code(xor.leading_zeros());
+        let leading_zeroes = LEADING_ZEROES[code as usize];
+        let trailing_zeroes = xor.trailing_zeros();
+
+        if trailing_zeroes > THRESHOLD {
+            // significant_digits is at most 64 - THRESHOLD - 1 so it always fits in 6 bits
+            let significant_digits = 64 - leading_zeroes - trailing_zeroes;
+            self.w.try_write_bits(0b01, 2)?;
+            self.w.try_write_bits(code, 3)?;
+            self.w.try_write_bits(u64::from(significant_digits), 6)?;
+            self.w
+                .try_write_bits(xor >> trailing_zeroes, significant_digits)?;
+            self.leading_zeroes = 65;
+        } else {
+            self.write_leading_zeroes_and_xor(xor, code)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_next_value_128(
+        &mut self,
+        window: &mut Window,
+        value_bits: u64,
+    ) -> Result<(), stream::Error> {
+        let position = window.find(value_bits);
+        let xor = value_bits ^ window.values[position];
+        window.push(value_bits);
+        self.value_bits = value_bits;
+
+        if xor == 0 {
+            self.w.try_write_bits(0b00, 2)?;
+            self.w
+                .try_write_bits(position as u64, PREVIOUS_VALUES_LOG2)?;
+            self.leading_zeroes = 65;
+            return Ok(());
+        }
+
+        let code = leading_zeroes_code(xor.leading_zeros());
+        let leading_zeroes = LEADING_ZEROES[code as usize];
+        let trailing_zeroes = xor.trailing_zeros();
+
+        if trailing_zeroes > THRESHOLD_128 {
+            let significant_digits = 64 - leading_zeroes - trailing_zeroes;
+            self.w.try_write_bits(0b01, 2)?;
+            self.w
+                .try_write_bits(position as u64, PREVIOUS_VALUES_LOG2)?;
+            self.w.try_write_bits(code, 3)?;
+            self.w.try_write_bits(u64::from(significant_digits), 6)?;
+            self.w
+                .try_write_bits(xor >> trailing_zeroes, significant_digits)?;
+            self.leading_zeroes = 65;
+        } else {
+            // the xor is with the immediately previous value, which the decoder knows the
+            // position of, so no index is stored
+            self.write_leading_zeroes_and_xor(xor, code)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_leading_zeroes_and_xor(&mut self, xor: u64, code: u64) -> Result<(), stream::Error> {
+        let leading_zeroes = LEADING_ZEROES[code as usize];
+
+        if leading_zeroes == self.leading_zeroes {
+            self.w.try_write_bits(0b10, 2)?;
+        } else {
+            self.w.try_write_bits(0b11, 2)?;
+            self.w.try_write_bits(code, 3)?;
+            self.leading_zeroes = leading_zeroes;
+        }
+
+        self.w.try_write_bits(xor, 64 - leading_zeroes)
+    }
+}
+
+impl<T> Encode for ChimpEncoder<T>
+where
+    T: Write,
+{
+    fn encode(&mut self, dp: DataPoint) {
+        // writers which can fail remember their first error and return it from `try_close`
+        let _ = self.write(dp);
+    }
+
+    fn try_encode(&mut self, dp: DataPoint) -> Result<(), Error> {
+        self.timestamps.check(dp.time, self.first)?;
+        self.write(dp).map_err(Error::Stream)
+    }
+
+    fn close(mut self) -> Box<[u8]> {
+        let _ = self.timestamps.write_end_marker(&mut self.w);
+        self.w.close()
+    }
+
+    fn try_close(mut self) -> Result<Box<[u8]>, Error> {
+        self.timestamps.write_end_marker(&mut self.w)?;
+        self.w.try_close().map_err(Error::Stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{leading_zeroes_code, ChimpEncoder};
+    use stream::BufferedWriter;
+    use {DataPoint, Encode, StdEncoder};
+
+    #[test]
+    fn round_leading_zeroes() {
+        assert_eq!(leading_zeroes_code(0), 0);
+        assert_eq!(leading_zeroes_code(7), 0);
+        assert_eq!(leading_zeroes_code(8), 1);
+        assert_eq!(leading_zeroes_code(17), 3);
+        assert_eq!(leading_zeroes_code(24), 7);
+        assert_eq!(leading_zeroes_code(63), 7);
+    }
+
+    #[test]
+    fn first_datapoint_matches_std_encoder() {
+        // the first DataPoint is stored exactly by both schemes
+        let dp = DataPoint::new(1482268055 + 10, 1.24);
+
+        let mut std = StdEncoder::new(1482268055, BufferedWriter::new());
+        std.encode(dp);
+
+        let mut chimp = ChimpEncoder::new(1482268055, BufferedWriter::new());
+        chimp.encode(dp);
+
+        let mut chimp_128 = ChimpEncoder::new_128(1482268055, BufferedWriter::new());
+        chimp_128.encode(dp);
+
+        let expected = std.close();
+        assert_eq!(chimp.close(), expected);
+        assert_eq!(chimp_128.close(), expected);
+    }
+
+    #[test]
+    fn encode_repeated_values() {
+        let start = 1482268055;
+        let mut chimp = ChimpEncoder::new(start, BufferedWriter::new());
+        let mut chimp_128 = ChimpEncoder::new_128(start, BufferedWriter::new());
+        for i in 1..101 {
+            chimp.encode(DataPoint::new(start + i * 10, 1.24));
+            chimp_128.encode(DataPoint::new(start + i * 10, 1.24));
+        }
+
+        // 64 bits for the start, 79 bits for the first DataPoint and 36 bits for the end marker,
+        // each repeated DataPoint needs a bit for its timestamp and the 00 flag, followed by a
+        // 7 bit index for the Chimp128 scheme
+        assert_eq!(
+            chimp.close().len(),
+            (64 + 79 + 99 * 3 + 36usize).div_ceil(8)
+        );
+        assert_eq!(
+            chimp_128.close().len(),
+            (64 + 79 + 99 * 10 + 36usize).div_ceil(8)
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use stream::{Error, Write};
+use Bit;
+
+// Counter wraps the writer of an encoder to count the bits written through it, so that the
+// encoder can record the offsets of its index and state without the writer knowing its position.
+// The count starts at the number of bits written before the encoder took the writer, such as its
+// header.
+#[derive(Debug, Clone)]
+pub(crate) struct Counter<W> {
+    w: W,
+    pub(crate) position: u64, // bits written since the stream began
+}
+
+impl<W> Counter<W> {
+    pub(crate) fn new(w: W, position: u64) -> Self {
+        Counter { w, position }
+    }
+
+    pub(crate) fn get_ref(&self) -> &W {
+        &self.w
+    }
+}
+
+impl<W> Write for Counter<W>
+where
+    W: Write,
+{
+    fn write_bit(&mut self, bit: Bit) {
+        self.position += 1;
+        self.w.write_bit(bit);
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        self.position += 8;
+        self.w.write_byte(byte);
+    }
+
+    fn write_bits(&mut self, bits: u64, num: u32) {
+        self.position += u64::from(num);
+        self.w.write_bits(bits, num);
+    }
+
+    fn close(self) -> Box<[u8]> {
+        self.w.close()
+    }
+
+    fn try_write_bit(&mut self, bit: Bit) -> Result<(), Error> {
+        self.position += 1;
+        self.w.try_write_bit(bit)
+    }
+
+    fn try_write_byte(&mut self, byte: u8) -> Result<(), Error> {
+        self.position += 8;
+        self.w.try_write_byte(byte)
+    }
+
+    fn try_write_bits(&mut self, bits: u64, num: u32) -> Result<(), Error> {
+        self.position += u64::from(num);
+        self.w.try_write_bits(bits, num)
+    }
+
+    fn try_close(self) -> Result<Box<[u8]>, Error> {
+        self.w.try_close()
+    }
+}
@@ -0,0 +1,171 @@
+use encode::timestamp::{TimestampWriter, CONTROL_BITS};
+use encode::Error;
+use header::{Header, ValueCodec};
+use stream;
+use stream::Write;
+use {Bit, IntDataPoint, TimeUnit};
+
+/// VALUE_BUCKET_LENS are the number of bits used to store the zigzag encoded delta of delta of a
+/// value in each bucket, in the order of their control bits 10, 110, 1110 and 1111
+pub(crate) const VALUE_BUCKET_LENS: [u32; 4] = [8, 16, 32, 64];
+
+// zigzag maps signed integers to unsigned integers so that numbers with a small absolute value
+// have a small encoding, i.e. 0 => 0, -1 => 1, 1 => 2, -2 => 3, and so on
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+/// IntEncoder
+///
+/// IntEncoder is used to encode `IntDataPoint`s. Timestamps are encoded the same way as
+/// `StdEncoder`, and values are encoded with the same delta of delta scheme, which stores
+/// counters which increase at a steady rate in a single bit per value.
+#[derive(Debug)]
+pub struct IntEncoder<T: Write> {
+    timestamps: TimestampWriter, // current time and time delta
+    value: i64,                  // current value
+    delta: i64,                  // current value delta
+
+    first: bool, // will next IntDataPoint be the first IntDataPoint encoded
+
+    w: T,
+}
+
+impl<T> IntEncoder<T>
+where
+    T: Write,
+{
+    /// new creates a new IntEncoder whose starting timestamp is `start` and writes its encoded
+    /// bytes to `w`
+    pub fn new(start: u64, w: T) -> Self {
+        IntEncoder::with_time_unit(start, TimeUnit::Second, w)
+    }
+
+    /// with_time_unit creates a new IntEncoder for timestamps with a resolution of `unit`, whose
+    /// starting timestamp is `start` and which writes its encoded bytes to `w`
+    pub fn with_time_unit(start: u64, unit: TimeUnit, w: T) -> Self {
+        IntEncoder::create(start, unit, w)
+    }
+
+    /// with_header creates a new IntEncoder like `with_time_unit`, but first writes a `Header`
+    /// recording `unit` so the stream can be decoded by `IntDecoder::with_header`
+    pub fn with_header(start: u64, unit: TimeUnit, mut w: T) -> Self {
+        // writers which can fail remember their first error and return it from `try_close`
+        let _ = Header::new(unit, ValueCodec::Integer).write(&mut w);
+        IntEncoder::create(start, unit, w)
+    }
+
+    fn create(start: u64, unit: TimeUnit, w: T) -> Self {
+        let mut e = IntEncoder {
+            timestamps: TimestampWriter::new(start, unit),
+            value: 0,
+            delta: 0,
+            first: true,
+            w,
+        };
+
+        // write timestamp header
+        let _ = e.timestamps.write_start(&mut e.w);
+
+        e
+    }
+
+    /// encode encodes `dp`, see `Encode::encode`
+    pub fn encode(&mut self, dp: IntDataPoint) {
+        // writers which can fail remember their first error and return it from `try_close`
+        let _ = self.write(dp);
+    }
+
+    /// try_encode encodes `dp` if its time can be represented in the stream, see
+    /// `Encode::try_encode`
+    pub fn try_encode(&mut self, dp: IntDataPoint) -> Result<(), Error> {
+        self.timestamps.check(dp.get_time(), self.first)?;
+        self.write(dp).map_err(Error::Stream)
+    }
+
+    /// close writes the end of the stream and returns its bytes, see `Encode::close`
+    pub fn close(mut self) -> Box<[u8]> {
+        let _ = self.timestamps.write_end_marker(&mut self.w);
+        self.w.close()
+    }
+
+    /// try_close is like `close`, but returns any error encountered while writing to the
+    /// underlying stream, see `Encode::try_close`
+    pub fn try_close(mut self) -> Result<Box<[u8]>, Error> {
+        self.timestamps.write_end_marker(&mut self.w)?;
+        self.w.try_close().map_err(Error::Stream)
+    }
+
+    fn write(&mut self, dp: IntDataPoint) -> Result<(), stream::Error> {
+        if self.first {
+            self.timestamps.write_first(&mut self.w, dp.get_time())?;
+
+            // store the first value exactly
+            self.value = dp.get_value();
+            self.w.try_write_bits(self.value as u64, 64)?;
+
+            self.first = false;
+            return Ok(());
+        }
+
+        self.timestamps.write_next(&mut self.w, dp.get_time())?;
+        self.write_next_value(dp.get_value())
+    }
+
+    fn write_next_value(&mut self, value: i64) -> Result<(), stream::Error> {
+        // wrapping arithmetic keeps the encoding lossless even when the deltas overflow, since
+        // the decoder wraps in the same way
+        let delta = value.wrapping_sub(self.value);
+        let dod = zigzag(delta.wrapping_sub(self.delta));
+
+        if dod == 0 {
+            self.w.try_write_bit(Bit::Zero)?;
+        } else {
+            // store the delta of delta in the smallest bucket it fits in
+            let bucket = (0..3)
+                .find(|&i| dod < 1 << VALUE_BUCKET_LENS[i])
+                .unwrap_or(3);
+
+            let (control, control_len) = CONTROL_BITS[bucket];
+            self.w.try_write_bits(control, control_len)?;
+            self.w.try_write_bits(dod, VALUE_BUCKET_LENS[bucket])?;
+        }
+
+        self.delta = delta;
+        self.value = value;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{zigzag, IntEncoder};
+    use stream::BufferedWriter;
+    use IntDataPoint;
+
+    #[test]
+    fn zigzag_values() {
+        assert_eq!(zigzag(0), 0);
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+        assert_eq!(zigzag(-2), 3);
+        assert_eq!(zigzag(i64::MAX), u64::MAX - 1);
+        assert_eq!(zigzag(i64::MIN), u64::MAX);
+    }
+
+    #[test]
+    fn encode_steady_counter() {
+        let start = 1482268055;
+        let mut e = IntEncoder::new(start, BufferedWriter::new());
+        for i in 1..101 {
+            e.encode(IntDataPoint::new(start + i * 10, 1000 + i as i64 * 50));
+        }
+
+        // 64 bits for the start, 79 bits for the first IntDataPoint and 36 bits for the end
+        // marker, the second IntDataPoint needs a bit for its timestamp and 10 bits for its
+        // value delta, then every other IntDataPoint needs a bit each for its timestamp and value
+        let bytes = e.close();
+        assert_eq!(bytes.len(), (64 + 79 + 11 + 98 * 2 + 36usize).div_ceil(8));
+    }
+}
@@ -1,11 +1,86 @@
+use std::{error, fmt};
+
+use stream;
 use DataPoint;
 
+/// Error
+///
+/// Error encapsulates the potential errors that can be encountered when encoding data
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The `DataPoint` is older than the start of the stream or the previous `DataPoint`.
+    OutOfOrder,
+    /// The delta between the start of the stream and the first `DataPoint` does not fit in the
+    /// bits reserved for it.
+    FirstDeltaTooLarge,
+    /// The delta of delta for the `DataPoint` does not fit in the largest bucket.
+    DeltaOfDeltaOverflow,
+    /// The writer an encoder was resumed with is not at the position its state was taken at.
+    StateMismatch,
+    /// The underlying stream could not be written to.
+    Stream(stream::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::OutOfOrder => write!(f, "DataPoint is older than the previous DataPoint"),
+            Error::FirstDeltaTooLarge => write!(f, "First delta is too large to encode"),
+            Error::DeltaOfDeltaOverflow => write!(f, "Delta of delta is too large to encode"),
+            Error::StateMismatch => write!(f, "Writer does not match the encoder state"),
+            Error::Stream(ref err) => write!(f, "Stream error: {}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Stream(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<stream::Error> for Error {
+    fn from(err: stream::Error) -> Error {
+        Error::Stream(err)
+    }
+}
+
 /// Encode
 ///
 /// Encode is the trait used to encode a stream of `DataPoint`s.
 pub trait Encode {
     fn encode(&mut self, dp: DataPoint);
+
+    /// Encode `dp` if it can be represented in the stream, otherwise return an error and leave
+    /// the encoder unchanged. Errors from the underlying stream are also returned, but may leave
+    /// a partially written `DataPoint` behind.
+    ///
+    /// The default implementation encodes `dp` with `encode` and never returns an error, so that
+    /// existing encoders keep working. Such encoders perform no validation: they never report
+    /// `OutOfOrder` or an overflow, and a `DataPoint` they cannot represent corrupts the stream.
+    /// Encoders should override it to check `dp` before writing it.
+    fn try_encode(&mut self, dp: DataPoint) -> Result<(), Error> {
+        self.encode(dp);
+        Ok(())
+    }
+
     fn close(self) -> Box<[u8]>;
+
+    /// Close the encoder, returning any error encountered while writing to the underlying stream.
+    fn try_close(self) -> Result<Box<[u8]>, Error>
+    where
+        Self: Sized,
+    {
+        Ok(self.close())
+    }
 }
 
+pub mod chimp_encoder;
+pub mod int_encoder;
 pub mod std_encoder;
+
+mod counter;
+mod timestamp;
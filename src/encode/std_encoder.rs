@@ -1,11 +1,18 @@
-use encode::Encode;
-use stream::Write;
-use {Bit, DataPoint};
+use agg::BlockSummary;
+use encode::counter::Counter;
+use encode::timestamp::TimestampWriter;
+use encode::{Encode, Error};
+use header::{Header, ValueCodec};
+use index::{BlockIndex, IndexEntry};
+use stream;
+use stream::{BufferedWriter, Position, SnapshotReader, Write};
+use {Bit, DataPoint, TimeUnit};
 
 // END_MARKER relies on the fact that when we encode the delta of delta for a number that requires
 // more than 12 bits we write four control bits 1111 followed by the 32 bits of the value. Since
 // encoding assumes the value is greater than 12 bits, we can store the value 0 to signal the end
-// of the stream
+// of the stream. Streams whose TimeUnit is finer than seconds use the same scheme, but follow the
+// control bits with as many zeroes as their largest bucket holds.
 
 /// END_MARKER is a special bit sequence used to indicate the end of the stream
 pub const END_MARKER: u64 = 0b1111_0000_0000_0000_0000_0000_0000_0000_0000;
@@ -13,14 +20,51 @@ pub const END_MARKER: u64 = 0b1111_0000_0000_0000_0000_0000_0000_0000_0000;
 /// END_MARKER_LEN is the length, in bits, of END_MARKER
 pub const END_MARKER_LEN: u32 = 36;
 
+/// EncoderState
+///
+/// EncoderState is a checkpoint of a `StdEncoder`, taken by `StdEncoder::state`, which can be
+/// saved, for example with serde, and later passed to `StdEncoder::resume` along with the bytes
+/// written so far to continue appending to the same stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct EncoderState {
+    time: u64,            // current time
+    delta: u64,           // current time delta
+    value_bits: u64,      // current float value as bits
+    leading_zeroes: u32,  // leading zeroes of the last xor stored
+    trailing_zeroes: u32, // trailing zeroes of the last xor stored
+    first: bool,          // will next DataPoint be the first DataPoint encoded
+    unit: TimeUnit,       // resolution of the timestamps
+    points: u64,          // number of DataPoints encoded
+    start_position: u64,  // bit offset of the starting timestamp
+    position: u64,        // number of bits written, excluding the end marker
+}
+
+impl EncoderState {
+    /// Get the number of bits the encoder had written, excluding the end marker. The bytes
+    /// returned by closing the encoder can be truncated to this length with
+    /// `BufferedWriter::from_bytes` to resume it.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Get the resolution of the timestamps in the stream.
+    pub fn time_unit(&self) -> TimeUnit {
+        self.unit
+    }
+
+    /// Get the number of `DataPoint`s the encoder had encoded.
+    pub fn points(&self) -> u64 {
+        self.points
+    }
+}
+
 /// StdEncoder
 ///
 /// StdEncoder is used to encode `DataPoint`s
 #[derive(Debug)]
 pub struct StdEncoder<T: Write> {
-    time: u64,       // current time
-    delta: u64,      // current time delta
-    value_bits: u64, // current float value as bits
+    timestamps: TimestampWriter, // current time and time delta
+    value_bits: u64,             // current float value as bits
 
     // store the number of leading and trailing zeroes in the current xor as u32 so we
     // don't have to do any conversions after calling `leading_zeros` and `trailing_zeros`
@@ -29,7 +73,12 @@ pub struct StdEncoder<T: Write> {
 
     first: bool, // will next DataPoint be the first DataPoint encoded
 
-    w: T,
+    points: u64,                   // number of DataPoints encoded
+    start_position: u64,           // bit offset of the starting timestamp
+    index: Option<BlockIndex>,     // sparse index of the stream, if enabled
+    summary: Option<BlockSummary>, // summary of the DataPoints encoded, if enabled
+
+    w: Counter<T>,
 }
 
 impl<T> StdEncoder<T>
@@ -39,82 +88,141 @@ where
     /// new creates a new StdEncoder whose starting timestamp is `start` and writes its encoded
     /// bytes to `w`
     pub fn new(start: u64, w: T) -> Self {
+        StdEncoder::with_time_unit(start, TimeUnit::Second, w)
+    }
+
+    /// with_time_unit creates a new StdEncoder for timestamps with a resolution of `unit`, whose
+    /// starting timestamp is `start` and which writes its encoded bytes to `w`. The unit is not
+    /// recorded in the stream, so it must be decoded by a `StdDecoder` with the same unit, see
+    /// `with_header` for an encoder which records it.
+    pub fn with_time_unit(start: u64, unit: TimeUnit, w: T) -> Self {
+        StdEncoder::create(start, unit, w, 0)
+    }
+
+    /// with_header creates a new StdEncoder like `with_time_unit`, but first writes a `Header`
+    /// recording `unit` so the stream can be decoded by `StdDecoder::with_header`
+    pub fn with_header(start: u64, unit: TimeUnit, mut w: T) -> Self {
+        // writers which can fail remember their first error and return it from `try_close`
+        let _ = Header::new(unit, ValueCodec::Gorilla).write(&mut w);
+        StdEncoder::create(start, unit, w, u64::from(Header::LEN))
+    }
+
+    // create creates a new StdEncoder which writes to `w`, after the `position` bits of the
+    // stream already written to it
+    fn create(start: u64, unit: TimeUnit, w: T, position: u64) -> Self {
         let mut e = StdEncoder {
-            time: start,
-            delta: 0,
+            timestamps: TimestampWriter::new(start, unit),
             value_bits: 0,
             leading_zeroes: 64,  // 64 is an initial sentinel value
             trailing_zeroes: 64, // 64 is an intitial sentinel value
             first: true,
-            w,
+            points: 0,
+            start_position: position,
+            index: None,
+            summary: None,
+            w: Counter::new(w, position),
         };
 
         // write timestamp header
-        e.w.write_bits(start, 64);
+        let _ = e.timestamps.write_start(&mut e.w);
 
         e
     }
 
-    fn write_first(&mut self, time: u64, value_bits: u64) {
-        self.delta = time - self.time;
-        self.time = time;
-        self.value_bits = value_bits;
+    /// state returns a checkpoint of the encoder which can be used to resume it later
+    pub fn state(&self) -> EncoderState {
+        EncoderState {
+            time: self.timestamps.time,
+            delta: self.timestamps.delta,
+            value_bits: self.value_bits,
+            leading_zeroes: self.leading_zeroes,
+            trailing_zeroes: self.trailing_zeroes,
+            first: self.first,
+            unit: self.timestamps.unit,
+            points: self.points,
+            start_position: self.start_position,
+            position: self.w.position,
+        }
+    }
 
-        // write one control bit so we can distinguish a stream which contains only an initial
-        // timestamp, this assumes the first bit of the END_MARKER is 1
-        self.w.write_bit(Bit::Zero);
+    /// enable_index makes the encoder record an `IndexEntry` after every `interval` `DataPoint`s,
+    /// counting from the start of the stream, which `StdDecoder::seek` can use to skip to a time
+    /// without decoding every `DataPoint` before it. An interval of zero is treated as one.
+    pub fn enable_index(&mut self, interval: u64) {
+        self.index = Some(BlockIndex::new(interval.max(1), self.start_position));
+    }
 
-        // store the first delta with 14 bits which is enough to span just over 4 hours
-        // if one wanted to use a window larger than 4 hours this size would increase
-        self.w.write_bits(self.delta, 14);
+    /// index returns the index recorded so far, if `enable_index` was called. It should be taken
+    /// before the encoder is closed, which does not add any entries.
+    pub fn index(&self) -> Option<&BlockIndex> {
+        self.index.as_ref()
+    }
 
-        // store the first value exactly
-        self.w.write_bits(self.value_bits, 64);
+    /// enable_summary makes the encoder compute a `BlockSummary` of the `DataPoint`s it encodes
+    /// from now on, which can be stored alongside the stream to answer aggregate queries over the
+    /// whole stream without decoding it
+    pub fn enable_summary(&mut self) {
+        self.summary = Some(BlockSummary::new());
+    }
 
-        self.first = true
+    /// summary returns the summary computed so far, if `enable_summary` was called
+    pub fn summary(&self) -> Option<&BlockSummary> {
+        self.summary.as_ref()
     }
 
-    fn write_next_timestamp(&mut self, time: u64) {
-        let delta = time - self.time; // current delta
-        let dod = delta.wrapping_sub(self.delta) as i32; // delta of delta
+    fn write(&mut self, dp: DataPoint) -> Result<(), stream::Error> {
+        let value_bits = dp.value.to_bits();
 
-        // store the delta of delta using variable length encoding
-        #[cfg_attr(feature = "cargo-clippy", allow(clippy::match_overlapping_arm))]
-        match dod {
-            0 => {
-                self.w.write_bit(Bit::Zero);
-            }
-            -63..=64 => {
-                self.w.write_bits(0b10, 2);
-                self.w.write_bits(dod as u64, 7);
-            }
-            -255..=256 => {
-                self.w.write_bits(0b110, 3);
-                self.w.write_bits(dod as u64, 9);
-            }
-            -2047..=2048 => {
-                self.w.write_bits(0b1110, 4);
-                self.w.write_bits(dod as u64, 12);
-            }
-            _ => {
-                self.w.write_bits(0b1111, 4);
-                self.w.write_bits(dod as u64, 32);
+        if self.first {
+            self.write_first(dp.time, value_bits)?;
+            self.first = false;
+        } else {
+            self.timestamps.write_next(&mut self.w, dp.time)?;
+            self.write_next_value(value_bits)?;
+        }
+
+        self.points += 1;
+        if let Some(ref mut index) = self.index {
+            if self.points.is_multiple_of(index.interval()) {
+                index.push(IndexEntry::new(
+                    self.w.position,
+                    self.timestamps.time,
+                    self.timestamps.delta,
+                    self.value_bits,
+                    self.leading_zeroes,
+                    self.trailing_zeroes,
+                ));
             }
         }
 
-        self.delta = delta;
-        self.time = time;
+        if let Some(ref mut summary) = self.summary {
+            summary.push(dp);
+        }
+
+        Ok(())
     }
 
-    fn write_next_value(&mut self, value_bits: u64) {
+    fn write_first(&mut self, time: u64, value_bits: u64) -> Result<(), stream::Error> {
+        self.timestamps.write_first(&mut self.w, time)?;
+        self.value_bits = value_bits;
+
+        // store the first value exactly
+        self.w.try_write_bits(self.value_bits, 64)?;
+
+        self.first = true;
+
+        Ok(())
+    }
+
+    fn write_next_value(&mut self, value_bits: u64) -> Result<(), stream::Error> {
         let xor = value_bits ^ self.value_bits;
         self.value_bits = value_bits;
 
         if xor == 0 {
             // if xor with previous value is zero just store single zero bit
-            self.w.write_bit(Bit::Zero);
+            self.w.try_write_bit(Bit::Zero)?;
         } else {
-            self.w.write_bit(Bit::One);
+            self.w.try_write_bit(Bit::One)?;
 
             let leading_zeroes = xor.leading_zeros();
             let trailing_zeroes = xor.trailing_zeros();
@@ -123,33 +231,92 @@ where
                 // if the number of leading and trailing zeroes in this xor are >= the leading and
                 // trailing zeroes in the previous xor then we only need to store a control bit and
                 // the significant digits of this xor
-                self.w.write_bit(Bit::Zero);
-                self.w.write_bits(
+                self.w.try_write_bit(Bit::Zero)?;
+                self.w.try_write_bits(
                     xor.wrapping_shr(self.trailing_zeroes),
                     64 - self.leading_zeroes - self.trailing_zeroes,
-                );
+                )?;
             } else {
                 // if the number of leading and trailing zeroes in this xor are not less than the
                 // leading and trailing zeroes in the previous xor then we store a control bit and
                 // use 6 bits to store the number of leading zeroes and 6 bits to store the number
                 // of significant digits before storing the significant digits themselves
 
-                self.w.write_bit(Bit::One);
-                self.w.write_bits(u64::from(leading_zeroes), 6);
+                self.w.try_write_bit(Bit::One)?;
+                self.w.try_write_bits(u64::from(leading_zeroes), 6)?;
 
                 // if significant_digits is 64 we cannot encode it using 6 bits, however since
                 // significant_digits is guaranteed to be at least 1 we can subtract 1 to ensure
                 // significant_digits can always be expressed with 6 bits or less
                 let significant_digits = 64 - leading_zeroes - trailing_zeroes;
-                self.w.write_bits(u64::from(significant_digits - 1), 6);
                 self.w
-                    .write_bits(xor.wrapping_shr(trailing_zeroes), significant_digits);
+                    .try_write_bits(u64::from(significant_digits - 1), 6)?;
+                self.w
+                    .try_write_bits(xor.wrapping_shr(trailing_zeroes), significant_digits)?;
 
                 // finally we need to update the number of leading and trailing zeroes
                 self.leading_zeroes = leading_zeroes;
                 self.trailing_zeroes = trailing_zeroes;
             }
         }
+
+        Ok(())
+    }
+}
+
+impl<T> StdEncoder<T>
+where
+    T: Write + Position,
+{
+    /// resume creates a StdEncoder which continues the stream `state` was taken from, writing to
+    /// `w`, which must already hold the bytes the encoder had written, without its end marker.
+    /// `BufferedWriter::from_bytes` can be used to create such a writer. Neither an index nor a
+    /// summary is resumed, see `enable_index` and `enable_summary`.
+    pub fn resume(state: EncoderState, w: T) -> Result<Self, Error> {
+        if w.bit_position() != state.position {
+            return Err(Error::StateMismatch);
+        }
+
+        let mut timestamps = TimestampWriter::new(state.time, state.unit);
+        timestamps.delta = state.delta;
+
+        Ok(StdEncoder {
+            timestamps,
+            value_bits: state.value_bits,
+            leading_zeroes: state.leading_zeroes,
+            trailing_zeroes: state.trailing_zeroes,
+            first: state.first,
+            points: state.points,
+            start_position: state.start_position,
+            index: None,
+            summary: None,
+            w: Counter::new(w, state.position),
+        })
+    }
+}
+
+impl StdEncoder<BufferedWriter> {
+    /// snapshot returns a copy of the bytes encoded so far followed by the end marker, so they can
+    /// be decoded while the encoder remains open. The encoder is left unchanged.
+    pub fn snapshot(&self) -> Box<[u8]> {
+        let mut w = self.w.get_ref().clone();
+        let _ = self.timestamps.write_end_marker(&mut w);
+        w.close()
+    }
+
+    /// snapshot_reader returns a reader over the same bytes as `snapshot`, which borrows the bytes
+    /// encoded so far rather than copying them. Only the last partially filled byte and the end
+    /// marker are copied.
+    pub fn snapshot_reader(&self) -> SnapshotReader<'_> {
+        let bytes = self.w.get_ref().get_ref();
+        let bit_len = self.w.position;
+        let split = (bit_len / 8) as usize;
+
+        let tail = bytes[split..].to_vec().into_boxed_slice();
+        let mut tail = BufferedWriter::from_bytes(tail, bit_len % 8);
+        let _ = self.timestamps.write_end_marker(&mut tail);
+
+        SnapshotReader::new(&bytes[..split], tail.close())
     }
 }
 
@@ -158,30 +325,34 @@ where
     T: Write,
 {
     fn encode(&mut self, dp: DataPoint) {
-        let value_bits = dp.value.to_bits();
-
-        if self.first {
-            self.write_first(dp.time, value_bits);
-            self.first = false;
-            return;
-        }
+        // writers which can fail remember their first error and return it from `try_close`
+        let _ = self.write(dp);
+    }
 
-        self.write_next_timestamp(dp.time);
-        self.write_next_value(value_bits)
+    fn try_encode(&mut self, dp: DataPoint) -> Result<(), Error> {
+        self.timestamps.check(dp.time, self.first)?;
+        self.write(dp).map_err(Error::Stream)
     }
 
     fn close(mut self) -> Box<[u8]> {
-        self.w.write_bits(END_MARKER, 36);
+        // writers which can fail remember their first error and return it from `try_close`
+        let _ = self.timestamps.write_end_marker(&mut self.w);
         self.w.close()
     }
+
+    fn try_close(mut self) -> Result<Box<[u8]>, Error> {
+        self.timestamps.write_end_marker(&mut self.w)?;
+        self.w.try_close().map_err(Error::Stream)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::StdEncoder;
-    use encode::Encode;
-    use stream::BufferedWriter;
-    use DataPoint;
+    use decode::{decode_all, Error as DecodeError};
+    use encode::{Encode, Error};
+    use stream::{BufferedReader, BufferedWriter, IoBitWriter, Write};
+    use {Bit, DataPoint, StdDecoder, TimeUnit};
 
     #[test]
     fn create_new_encoder() {
@@ -244,4 +415,229 @@ mod tests {
 
         assert_eq!(bytes[..], expected_bytes[..]);
     }
+
+    #[test]
+    fn try_encode_rejects_out_of_order_datapoints() {
+        let w = BufferedWriter::new();
+        let start_time = 1482268055; // 2016-12-20T21:07:35+00:00
+        let mut e = StdEncoder::new(start_time, w);
+
+        let before_start = DataPoint::new(start_time - 1, 1.24);
+        assert_eq!(e.try_encode(before_start).err().unwrap(), Error::OutOfOrder);
+
+        e.try_encode(DataPoint::new(start_time + 20, 1.24)).unwrap();
+
+        let before_previous = DataPoint::new(start_time + 10, 1.98);
+        assert_eq!(
+            e.try_encode(before_previous).err().unwrap(),
+            Error::OutOfOrder
+        );
+
+        // the rejected datapoints should leave no trace in the stream
+        let bytes = e.close();
+
+        let w = BufferedWriter::new();
+        let mut expected = StdEncoder::new(start_time, w);
+        expected.encode(DataPoint::new(start_time + 20, 1.24));
+
+        assert_eq!(bytes[..], expected.close()[..]);
+    }
+
+    #[test]
+    fn try_encode_rejects_large_first_delta() {
+        let w = BufferedWriter::new();
+        let start_time = 1482268055; // 2016-12-20T21:07:35+00:00
+        let mut e = StdEncoder::new(start_time, w);
+
+        let dp = DataPoint::new(start_time + (1 << 14), 1.24);
+        assert_eq!(e.try_encode(dp).err().unwrap(), Error::FirstDeltaTooLarge);
+
+        let dp = DataPoint::new(start_time + (1 << 14) - 1, 1.24);
+        assert!(e.try_encode(dp).is_ok());
+    }
+
+    #[test]
+    fn try_encode_rejects_delta_of_delta_overflow() {
+        let w = BufferedWriter::new();
+        let start_time = 0;
+        let mut e = StdEncoder::new(start_time, w);
+
+        e.try_encode(DataPoint::new(10, 1.24)).unwrap();
+        e.try_encode(DataPoint::new(20, 1.24)).unwrap();
+
+        let dp = DataPoint::new(30 + (1 << 31) + 1, 1.24);
+        assert_eq!(e.try_encode(dp).err().unwrap(), Error::DeltaOfDeltaOverflow);
+
+        let dp = DataPoint::new(30 + (1 << 31), 1.24);
+        assert!(e.try_encode(dp).is_ok());
+
+        // the largest accepted delta of delta should survive a round trip
+        let datapoints = decode_all(&e.close()).unwrap();
+        let expected_datapoints = vec![
+            DataPoint::new(10, 1.24),
+            DataPoint::new(20, 1.24),
+            DataPoint::new(30 + (1 << 31), 1.24),
+        ];
+        assert_eq!(datapoints, expected_datapoints);
+    }
+
+    #[test]
+    fn encode_to_io_writer() {
+        let start_time = 1482268055; // 2016-12-20T21:07:35+00:00
+        let datapoints = [
+            DataPoint::new(1482268055 + 10, 1.24),
+            DataPoint::new(1482268055 + 20, 1.98),
+            DataPoint::new(1482268055 + 32, 2.37),
+        ];
+
+        let mut buf = Vec::new();
+        {
+            let mut e = StdEncoder::new(start_time, IoBitWriter::new(&mut buf));
+            for dp in &datapoints {
+                e.try_encode(*dp).unwrap();
+            }
+
+            // every byte has been written to buf so there is nothing left to return
+            assert!(e.try_close().unwrap().is_empty());
+        }
+
+        let mut e = StdEncoder::new(start_time, BufferedWriter::new());
+        for dp in &datapoints {
+            e.encode(*dp);
+        }
+
+        assert_eq!(buf[..], e.close()[..]);
+    }
+
+    #[test]
+    fn resume_from_state() {
+        let start = 1482268055000;
+        let dps: Vec<DataPoint> = (0..300)
+            .map(|i| DataPoint::new(start + 1000 + i * 1000 + i % 11, (i % 13) as f64 / 4.0))
+            .collect();
+
+        let mut expected =
+            StdEncoder::with_header(start, TimeUnit::Millisecond, BufferedWriter::new());
+        for &dp in &dps {
+            expected.encode(dp);
+        }
+        let expected = expected.close();
+
+        // checkpoint the encoder at several points, including before the first DataPoint, and
+        // resume it from the closed bytes
+        for &split in &[0, 1, 2, 150, 300] {
+            let mut e =
+                StdEncoder::with_header(start, TimeUnit::Millisecond, BufferedWriter::new());
+            for &dp in &dps[..split] {
+                e.encode(dp);
+            }
+
+            let state = e.state();
+            let bytes = e.close();
+
+            let w = BufferedWriter::from_bytes(bytes, state.position());
+            let mut e = StdEncoder::resume(state, w).unwrap();
+            for &dp in &dps[split..] {
+                e.try_encode(dp).unwrap();
+            }
+
+            assert_eq!(e.close(), expected);
+        }
+
+        let r = BufferedReader::new(expected);
+        let actual: Result<Vec<DataPoint>, DecodeError> =
+            StdDecoder::with_header(r).unwrap().collect();
+        assert_eq!(actual.unwrap(), dps);
+    }
+
+    #[test]
+    fn resume_rejects_mismatched_writer() {
+        let mut e = StdEncoder::new(1482268055, BufferedWriter::new());
+        e.encode(DataPoint::new(1482268065, 1.24));
+        let state = e.state();
+
+        // the end marker must be removed before resuming
+        let bytes = e.close();
+        let w = BufferedWriter::from_bytes(bytes, u64::MAX);
+        assert_eq!(
+            StdEncoder::resume(state, w).err().unwrap(),
+            Error::StateMismatch
+        );
+    }
+
+    #[test]
+    fn snapshot_open_encoder() {
+        let start = 1482268055;
+        let dps: Vec<DataPoint> = (0..200)
+            .map(|i| DataPoint::new(start + 10 + i * 10 + i % 7, (i % 23) as f64 * 0.1))
+            .collect();
+
+        let mut e = StdEncoder::new(start, BufferedWriter::new());
+        let bytes = e.snapshot();
+        assert_eq!(decode_all(&bytes).unwrap(), vec![]);
+
+        for (i, &dp) in dps.iter().enumerate() {
+            e.encode(dp);
+
+            // the snapshot decodes to everything encoded so far, whether or not the last byte
+            // is full, and does not disturb the encoder
+            let bytes = e.snapshot();
+            assert_eq!(decode_all(&bytes).unwrap(), dps[..i + 1].to_vec());
+
+            let decoder = StdDecoder::new(e.snapshot_reader());
+            let actual: Result<Vec<DataPoint>, DecodeError> = decoder.collect();
+            assert_eq!(actual.unwrap(), dps[..i + 1].to_vec());
+        }
+
+        let mut expected = StdEncoder::new(start, BufferedWriter::new());
+        for &dp in &dps {
+            expected.encode(dp);
+        }
+
+        assert_eq!(e.snapshot(), expected.close());
+    }
+
+    // MinimalWriter only implements the required methods of Write
+    struct MinimalWriter(BufferedWriter);
+
+    impl Write for MinimalWriter {
+        fn write_bit(&mut self, bit: Bit) {
+            self.0.write_bit(bit);
+        }
+
+        fn write_byte(&mut self, byte: u8) {
+            self.0.write_byte(byte);
+        }
+
+        fn write_bits(&mut self, bits: u64, num: u32) {
+            self.0.write_bits(bits, num);
+        }
+
+        fn close(self) -> Box<[u8]> {
+            self.0.close()
+        }
+    }
+
+    #[test]
+    fn index_with_minimal_writer() {
+        let start = 1482268055;
+        let mut expected = StdEncoder::with_header(start, TimeUnit::Second, BufferedWriter::new());
+        let mut actual = StdEncoder::with_header(
+            start,
+            TimeUnit::Second,
+            MinimalWriter(BufferedWriter::new()),
+        );
+        expected.enable_index(8);
+        actual.enable_index(8);
+
+        for i in 0..100 {
+            let dp = DataPoint::new(start + 10 + i * 10 + i % 3, (i % 17) as f64 * 1.5);
+            expected.encode(dp);
+            actual.encode(dp);
+        }
+
+        assert_eq!(actual.index(), expected.index());
+        assert_eq!(actual.state(), expected.state());
+        assert_eq!(actual.close(), expected.close());
+    }
 }
@@ -0,0 +1,110 @@
+use encode::Error;
+use stream;
+use stream::Write;
+use {Bit, TimeUnit};
+
+// CONTROL_BITS are the control bits, and their length, which precede each delta of delta bucket
+pub(crate) const CONTROL_BITS: [(u64, u32); 4] = [(0b10, 2), (0b110, 3), (0b1110, 4), (0b1111, 4)];
+
+// fits returns true if `dod` can be stored in a bucket which is `len` bits long, given that the
+// decoder only sign extends values greater than 2^(len - 1)
+fn fits(dod: i128, len: u32) -> bool {
+    let bound = 1i128 << (len - 1);
+    dod > -bound && dod <= bound
+}
+
+// TimestampWriter encodes the timestamps of a stream using the delta of delta scheme from the
+// Gorilla paper. It is shared by the encoders, which only differ in how they encode values.
+#[derive(Debug, Clone)]
+pub(crate) struct TimestampWriter {
+    pub(crate) time: u64,      // current time
+    pub(crate) delta: u64,     // current time delta
+    pub(crate) unit: TimeUnit, // resolution of the timestamps
+}
+
+impl TimestampWriter {
+    pub(crate) fn new(start: u64, unit: TimeUnit) -> Self {
+        TimestampWriter {
+            time: start,
+            delta: 0,
+            unit,
+        }
+    }
+
+    // check returns an error if `time` cannot be written to the stream
+    pub(crate) fn check(&self, time: u64, first: bool) -> Result<(), Error> {
+        if time < self.time {
+            return Err(Error::OutOfOrder);
+        }
+
+        let delta = time - self.time;
+        if first {
+            if delta >= 1 << self.unit.first_delta_len() {
+                return Err(Error::FirstDeltaTooLarge);
+            }
+        } else {
+            let dod = i128::from(delta) - i128::from(self.delta);
+            if !fits(dod, self.unit.bucket_lens()[3]) {
+                return Err(Error::DeltaOfDeltaOverflow);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn write_start<W: Write>(&self, w: &mut W) -> Result<(), stream::Error> {
+        w.try_write_bits(self.time, 64)
+    }
+
+    pub(crate) fn write_first<W: Write>(
+        &mut self,
+        w: &mut W,
+        time: u64,
+    ) -> Result<(), stream::Error> {
+        self.delta = time - self.time;
+        self.time = time;
+
+        // write one control bit so we can distinguish a stream which contains only an initial
+        // timestamp, this assumes the first bit of the END_MARKER is 1
+        w.try_write_bit(Bit::Zero)?;
+
+        // store the first delta with enough bits to span just over 4 hours, which is 14 bits
+        // for timestamps in seconds, if one wanted to use a window larger than 4 hours this size
+        // would increase
+        w.try_write_bits(self.delta, self.unit.first_delta_len())
+    }
+
+    pub(crate) fn write_next<W: Write>(
+        &mut self,
+        w: &mut W,
+        time: u64,
+    ) -> Result<(), stream::Error> {
+        let delta = time - self.time; // current delta
+        let dod = delta.wrapping_sub(self.delta) as i64; // delta of delta
+
+        if dod == 0 {
+            w.try_write_bit(Bit::Zero)?;
+        } else {
+            // store the delta of delta in the smallest bucket it fits in
+            let lens = self.unit.bucket_lens();
+            let bucket = (0..3)
+                .find(|&i| fits(i128::from(dod), lens[i]))
+                .unwrap_or(3);
+
+            let (control, control_len) = CONTROL_BITS[bucket];
+            w.try_write_bits(control, control_len)?;
+            w.try_write_bits(dod as u64, lens[bucket])?;
+        }
+
+        self.delta = delta;
+        self.time = time;
+
+        Ok(())
+    }
+
+    pub(crate) fn write_end_marker<W: Write>(&self, w: &mut W) -> Result<(), stream::Error> {
+        // for timestamps in seconds this writes END_MARKER
+        w.try_write_bits(0b1111, 4)?;
+        w.try_write_bits(0, self.unit.bucket_lens()[3])
+    }
+}
@@ -0,0 +1,680 @@
+use std::{error, fmt, io};
+
+use header::{time_unit_from_u8, time_unit_to_u8};
+use store::Block;
+use stream;
+use stream::SliceReader;
+use {StdDecoder, TimeUnit};
+
+/// MAGIC is the sequence of bytes, "tszf", which begins and ends every block file
+pub const MAGIC: u32 = 0x7473_7a66;
+
+/// FORMAT_VERSION is the version of the block file format written by this crate
+pub const FORMAT_VERSION: u8 = 1;
+
+// HEADER_LEN is the length, in bytes, of the magic number, format version and time unit which
+// begin a file
+const HEADER_LEN: usize = 6;
+
+// TRAILER_LEN is the length, in bytes, of the footer offset, footer checksum and magic number
+// which end a file
+const TRAILER_LEN: usize = 16;
+
+// MIN_ENTRY_LEN is the length, in bytes, of a footer entry with an empty series key
+const MIN_ENTRY_LEN: usize = 46;
+
+/// Error
+///
+/// Error encapsulates the potential errors that can be encountered when writing or reading a
+/// block file
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The underlying file could not be written to or read from.
+    Stream(stream::Error),
+    /// The file does not begin and end with `MAGIC`.
+    BadMagic,
+    /// The file was written with a format version this crate cannot read.
+    UnsupportedVersion(u8),
+    /// The footer of the file is malformed, or a series key is too long to store in it.
+    InvalidFooter,
+    /// The block, or footer, at the offset does not match its checksum.
+    ChecksumMismatch(u64),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Stream(ref err) => write!(f, "Stream error: {}", err),
+            Error::BadMagic => write!(f, "File is not a block file"),
+            Error::UnsupportedVersion(version) => {
+                write!(f, "Unsupported block file format version {}", version)
+            }
+            Error::InvalidFooter => write!(f, "Failed to parse block file footer"),
+            Error::ChecksumMismatch(offset) => {
+                write!(f, "Checksum mismatch for block at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Stream(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<stream::Error> for Error {
+    fn from(err: stream::Error) -> Error {
+        Error::Stream(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Stream(stream::Error::Io(err))
+    }
+}
+
+/// FileEntry
+///
+/// FileEntry is the entry in the footer of a block file which describes a single block: the
+/// series it belongs to, the window of time it covers and where its bytes are in the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntry {
+    key: String,   // key of the series
+    start: u64,    // start of the window, inclusive
+    end: u64,      // end of the window, exclusive
+    points: u64,   // number of DataPoints in the block
+    offset: u64,   // offset of the block in bytes from the start of the file
+    len: u64,      // length of the block in bytes
+    checksum: u32, // CRC-32 of the block
+}
+
+impl FileEntry {
+    /// Get the key of the series the block belongs to.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the start of the window the block covers, which is also the starting timestamp of
+    /// its stream.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Get the end of the window the block covers, which no `DataPoint` in it reaches.
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Get the number of `DataPoint`s in the block.
+    pub fn points(&self) -> u64 {
+        self.points
+    }
+
+    /// Get the offset, in bytes from the start of the file, of the block.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the length of the block in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Check if the block is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the CRC-32 checksum of the block.
+    pub fn checksum(&self) -> u32 {
+        self.checksum
+    }
+}
+
+/// FileWriter
+///
+/// FileWriter writes the blocks of many series into a single file. Each block is the output of
+/// `StdEncoder::close` for an encoder created without a `Header`, and blocks are appended in the
+/// order they are written. When the writer is finished it writes a footer with a `FileEntry` for
+/// every block, ordered by series key and then start time, so that a `FileReader` can find the
+/// blocks of a series without reading the others.
+///
+/// The file begins with `MAGIC`, followed by a byte each for the format version and time unit,
+/// and ends with the offset and checksum of the footer followed by `MAGIC` again. Every integer
+/// is stored big-endian.
+#[derive(Debug)]
+pub struct FileWriter<W: io::Write> {
+    unit: TimeUnit,
+    entries: Vec<FileEntry>,
+    offset: u64, // number of bytes written
+    w: W,
+}
+
+impl<W> FileWriter<W>
+where
+    W: io::Write,
+{
+    /// new creates a new FileWriter which writes blocks whose timestamps are in seconds to `w`
+    pub fn new(w: W) -> Result<Self, Error> {
+        FileWriter::with_time_unit(w, TimeUnit::Second)
+    }
+
+    /// with_time_unit creates a new FileWriter which writes blocks whose timestamps have a
+    /// resolution of `unit` to `w`
+    pub fn with_time_unit(mut w: W, unit: TimeUnit) -> Result<Self, Error> {
+        let mut header = [0; HEADER_LEN];
+        header[..4].copy_from_slice(&MAGIC.to_be_bytes());
+        header[4] = FORMAT_VERSION;
+        header[5] = time_unit_to_u8(unit);
+        w.write_all(&header)?;
+
+        Ok(FileWriter {
+            unit,
+            entries: Vec::new(),
+            offset: HEADER_LEN as u64,
+            w,
+        })
+    }
+
+    /// Get the resolution of the timestamps of the blocks in the file.
+    pub fn time_unit(&self) -> TimeUnit {
+        self.unit
+    }
+
+    /// append appends `bytes`, a block of the series identified by `key` which holds `points`
+    /// `DataPoint`s whose times are at least `start` and less than `end`
+    pub fn append(
+        &mut self,
+        key: &str,
+        start: u64,
+        end: u64,
+        points: u64,
+        bytes: &[u8],
+    ) -> Result<(), Error> {
+        if key.len() > usize::from(u16::MAX) {
+            return Err(Error::InvalidFooter);
+        }
+
+        self.w.write_all(bytes)?;
+        self.entries.push(FileEntry {
+            key: key.to_string(),
+            start,
+            end,
+            points,
+            offset: self.offset,
+            len: bytes.len() as u64,
+            checksum: crc32(bytes),
+        });
+        self.offset += bytes.len() as u64;
+
+        Ok(())
+    }
+
+    /// append_block appends a closed `Block` of a `SeriesStore` for the series identified by
+    /// `key`
+    pub fn append_block(&mut self, key: &str, block: &Block) -> Result<(), Error> {
+        self.append(
+            key,
+            block.start(),
+            block.end(),
+            block.points(),
+            block.bytes(),
+        )
+    }
+
+    /// finish writes the footer and returns the underlying writer
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.entries
+            .sort_by(|a, b| (&a.key, a.start).cmp(&(&b.key, b.start)));
+
+        let mut footer = Vec::new();
+        footer.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+        for entry in &self.entries {
+            footer.extend_from_slice(&(entry.key.len() as u16).to_be_bytes());
+            footer.extend_from_slice(entry.key.as_bytes());
+            for &n in &[
+                entry.start,
+                entry.end,
+                entry.points,
+                entry.offset,
+                entry.len,
+            ] {
+                footer.extend_from_slice(&n.to_be_bytes());
+            }
+            footer.extend_from_slice(&entry.checksum.to_be_bytes());
+        }
+
+        footer.extend_from_slice(&self.offset.to_be_bytes());
+        footer.extend_from_slice(&crc32(&footer[..footer.len() - 8]).to_be_bytes());
+        footer.extend_from_slice(&MAGIC.to_be_bytes());
+
+        self.w.write_all(&footer)?;
+        self.w.flush()?;
+
+        Ok(self.w)
+    }
+}
+
+/// FileReader
+///
+/// FileReader reads a file written by a `FileWriter` from a borrowed slice of bytes, such as a
+/// memory-mapped file. Opening the file only parses its footer, and the blocks of a series are
+/// found by binary search and decoded in place without copying them.
+#[derive(Debug)]
+pub struct FileReader<'a> {
+    bytes: &'a [u8],
+    unit: TimeUnit,
+    entries: Vec<FileEntry>,
+}
+
+impl<'a> FileReader<'a> {
+    /// open parses the header and footer of the file in `bytes`
+    pub fn open(bytes: &'a [u8]) -> Result<Self, Error> {
+        if bytes.len() < HEADER_LEN + TRAILER_LEN {
+            return Err(Error::BadMagic);
+        }
+
+        let mut header = Cursor::new(&bytes[..HEADER_LEN]);
+        let mut trailer = Cursor::new(&bytes[bytes.len() - TRAILER_LEN..]);
+        if header.u32()? != MAGIC || trailer.skip(12)?.u32()? != MAGIC {
+            return Err(Error::BadMagic);
+        }
+
+        let version = header.u8()?;
+        if version != FORMAT_VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let unit = time_unit_from_u8(header.u8()?).ok_or(Error::InvalidFooter)?;
+
+        let mut trailer = Cursor::new(&bytes[bytes.len() - TRAILER_LEN..]);
+        let footer_offset = trailer.u64()?;
+        let footer_checksum = trailer.u32()?;
+
+        let footer_end = bytes.len() - TRAILER_LEN;
+        if footer_offset < HEADER_LEN as u64 || footer_offset > footer_end as u64 {
+            return Err(Error::InvalidFooter);
+        }
+        let footer = &bytes[footer_offset as usize..footer_end];
+        if crc32(footer) != footer_checksum {
+            return Err(Error::ChecksumMismatch(footer_offset));
+        }
+
+        // the count is not trusted to size the entries since the footer may not hold that many
+        let capacity = footer.len() / MIN_ENTRY_LEN;
+        let mut footer = Cursor::new(footer);
+        let count = footer.u32()?;
+        let mut entries = Vec::with_capacity((count as usize).min(capacity));
+        for _ in 0..count {
+            let key_len = footer.u16()?;
+            let key = String::from_utf8(footer.bytes(usize::from(key_len))?.to_vec())
+                .map_err(|_| Error::InvalidFooter)?;
+            let entry = FileEntry {
+                key,
+                start: footer.u64()?,
+                end: footer.u64()?,
+                points: footer.u64()?,
+                offset: footer.u64()?,
+                len: footer.u64()?,
+                checksum: footer.u32()?,
+            };
+
+            let in_bounds = entry
+                .offset
+                .checked_add(entry.len)
+                .is_some_and(|end| entry.offset >= HEADER_LEN as u64 && end <= footer_offset);
+            if !in_bounds {
+                return Err(Error::InvalidFooter);
+            }
+
+            entries.push(entry);
+        }
+
+        Ok(FileReader {
+            bytes,
+            unit,
+            entries,
+        })
+    }
+
+    /// Get the resolution of the timestamps of the blocks in the file.
+    pub fn time_unit(&self) -> TimeUnit {
+        self.unit
+    }
+
+    /// Get the entries of every block in the file, which are ordered by series key and then
+    /// start time.
+    pub fn entries(&self) -> &[FileEntry] {
+        &self.entries
+    }
+
+    /// find returns the entries of the blocks of the series identified by `key` which overlap
+    /// the range from `start`, inclusive, to `end`, exclusive, ordered by start time. The blocks
+    /// of a series may overlap each other, such as when late `DataPoint`s were written to a
+    /// block of their own, so the blocks returned may do so too.
+    pub fn find(&self, key: &str, start: u64, end: u64) -> Vec<&FileEntry> {
+        if start >= end {
+            return Vec::new();
+        }
+
+        let lo = self
+            .entries
+            .partition_point(|entry| entry.key.as_str() < key);
+        let hi = self
+            .entries
+            .partition_point(|entry| entry.key.as_str() <= key);
+        let series = &self.entries[lo..hi];
+
+        // entries are ordered by start, so every entry after those which start before `end` is
+        // too late, but an entry which ends before `start` may be followed by one which does not
+        let hi = series.partition_point(|entry| entry.start < end);
+        series[..hi]
+            .iter()
+            .filter(|entry| entry.end > start)
+            .collect()
+    }
+
+    /// decoder verifies the checksum of the block described by `entry` and returns a
+    /// `StdDecoder` which reads it in place
+    pub fn decoder(&self, entry: &FileEntry) -> Result<StdDecoder<SliceReader<'a>>, Error> {
+        let bytes = self.block(entry)?;
+        Ok(StdDecoder::with_time_unit(
+            SliceReader::new(bytes),
+            self.unit,
+        ))
+    }
+
+    /// decoders returns a `StdDecoder` for each block of the series identified by `key` which
+    /// overlaps the range from `start` to `end`, ordered by start time, see `find`. The blocks may
+    /// hold `DataPoint`s outside of the range.
+    pub fn decoders(
+        &self,
+        key: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<StdDecoder<SliceReader<'a>>>, Error> {
+        self.find(key, start, end)
+            .into_iter()
+            .map(|entry| self.decoder(entry))
+            .collect()
+    }
+
+    // block returns the bytes of the block described by `entry` after verifying its checksum
+    fn block(&self, entry: &FileEntry) -> Result<&'a [u8], Error> {
+        let block = self
+            .bytes
+            .get(entry.offset as usize..(entry.offset + entry.len) as usize)
+            .ok_or(Error::InvalidFooter)?;
+        if crc32(block) != entry.checksum {
+            return Err(Error::ChecksumMismatch(entry.offset));
+        }
+
+        Ok(block)
+    }
+}
+
+// Cursor reads big-endian integers from a slice of bytes
+struct Cursor<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Cursor { bytes }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() < len {
+            return Err(Error::InvalidFooter);
+        }
+
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<&mut Self, Error> {
+        self.bytes(len)?;
+        Ok(self)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let mut buf = [0; 2];
+        buf.copy_from_slice(self.bytes(2)?);
+        Ok(u16::from_be_bytes(buf))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_be_bytes(buf))
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_be_bytes(buf))
+    }
+}
+
+// CRC_TABLE is the lookup table for the CRC-32 (IEEE 802.3) polynomial in reflected form
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+// crc32 returns the CRC-32 checksum of `bytes`
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| {
+        CRC_TABLE[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crc32, Error, FileReader, FileWriter, HEADER_LEN, TRAILER_LEN};
+    use decode::Error as DecodeError;
+    use decode::{Context, FieldKind};
+    use store::SeriesStore;
+    use {DataPoint, TimeUnit};
+
+    fn write_file() -> (Vec<u8>, SeriesStore<&'static str>) {
+        let mut store = SeriesStore::with_time_unit(3600 * 1000, TimeUnit::Millisecond).unwrap();
+        for i in 0..5000u64 {
+            let time = i * 10_000 + i % 17;
+            store
+                .insert("cpu", DataPoint::new(time, (i % 9) as f64 * 0.25))
+                .unwrap();
+            store
+                .insert("mem", DataPoint::new(time + 3, 1e6 - i as f64))
+                .unwrap();
+        }
+
+        let mut writer = FileWriter::with_time_unit(Vec::new(), TimeUnit::Millisecond).unwrap();
+        // write the series interleaved to check the footer is sorted
+        for (a, b) in store.blocks("mem").iter().zip(store.blocks("cpu")) {
+            writer.append_block("mem", a).unwrap();
+            writer.append_block("cpu", b).unwrap();
+        }
+
+        (writer.finish().unwrap(), store)
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn write_and_read_file() {
+        let (bytes, store) = write_file();
+        let reader = FileReader::open(&bytes).unwrap();
+        assert_eq!(reader.time_unit(), TimeUnit::Millisecond);
+        assert_eq!(reader.entries().len(), 2 * store.blocks("cpu").len());
+        assert!(reader
+            .entries()
+            .windows(2)
+            .all(|pair| { (pair[0].key(), pair[0].start()) < (pair[1].key(), pair[1].start()) }));
+
+        for &key in &["cpu", "mem"] {
+            for &(start, end) in &[
+                (0, u64::MAX),
+                (3600 * 1000, 3600 * 2000),
+                (7_300_000, 9_000_000),
+            ] {
+                let mut actual = Vec::new();
+                for decoder in reader.decoders(key, start, end).unwrap() {
+                    for dp in decoder {
+                        let dp = dp.unwrap();
+                        if dp.get_time() >= start && dp.get_time() < end {
+                            actual.push(dp);
+                        }
+                    }
+                }
+
+                // the file only holds the closed blocks of the store
+                let closed_end = store.blocks(key).last().unwrap().end();
+                let expected = store.query(key, start, end.min(closed_end)).unwrap();
+                assert!(!expected.is_empty());
+                assert_eq!(actual, expected);
+            }
+
+            let entries = reader.find(key, 3600 * 1000, 3600 * 1000 + 1);
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].start(), 3600 * 1000);
+        }
+
+        assert!(reader.find("disk", 0, u64::MAX).is_empty());
+        assert!(reader.find("cpu", 5, 5).is_empty());
+    }
+
+    #[test]
+    fn find_overlapping_blocks() {
+        // a long block followed by blocks which start inside it, one of which ends before the
+        // others, as writing late DataPoints to blocks of their own leaves them
+        let mut writer = FileWriter::new(Vec::new()).unwrap();
+        writer.append("cpu", 0, 3600, 1, &[1]).unwrap();
+        writer.append("cpu", 600, 1200, 1, &[2]).unwrap();
+        writer.append("cpu", 1800, 2400, 1, &[3]).unwrap();
+        writer.append("cpu", 3600, 7200, 1, &[4]).unwrap();
+        let bytes = writer.finish().unwrap();
+        let reader = FileReader::open(&bytes).unwrap();
+
+        let starts = |start, end| -> Vec<u64> {
+            reader
+                .find("cpu", start, end)
+                .iter()
+                .map(|entry| entry.start())
+                .collect()
+        };
+        assert_eq!(starts(0, u64::MAX), vec![0, 600, 1800, 3600]);
+        assert_eq!(starts(2000, 2001), vec![0, 1800]);
+        assert_eq!(starts(1200, 1800), vec![0]);
+        assert_eq!(starts(3000, 4000), vec![0, 3600]);
+        assert_eq!(starts(7200, 8000), vec![]);
+    }
+
+    #[test]
+    fn detect_corruption() {
+        let (mut bytes, _) = write_file();
+
+        // flipping a bit in a block is detected when the block is decoded
+        let offset = {
+            let reader = FileReader::open(&bytes).unwrap();
+            reader.find("mem", 0, 1)[0].offset()
+        };
+        bytes[offset as usize + 3] ^= 0x10;
+        let reader = FileReader::open(&bytes).unwrap();
+        assert_eq!(
+            reader.decoders("mem", 0, 1).err().unwrap(),
+            Error::ChecksumMismatch(offset)
+        );
+        assert!(reader.decoders("cpu", 0, 1).is_ok());
+
+        // as is flipping a bit in the footer when the file is opened
+        let len = bytes.len();
+        bytes[len - 20] ^= 0x01;
+        match FileReader::open(&bytes) {
+            Err(Error::ChecksumMismatch(_)) => {}
+            other => panic!("expected a checksum mismatch, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reject_invalid_files() {
+        let (bytes, _) = write_file();
+
+        assert_eq!(FileReader::open(&[]).err().unwrap(), Error::BadMagic);
+        assert_eq!(
+            FileReader::open(&bytes[..bytes.len() - 1]).err().unwrap(),
+            Error::BadMagic
+        );
+
+        let mut newer = bytes.clone();
+        newer[4] = 2;
+        assert_eq!(
+            FileReader::open(&newer).err().unwrap(),
+            Error::UnsupportedVersion(2)
+        );
+
+        // an empty file has no blocks
+        let empty = FileWriter::new(Vec::new()).unwrap().finish().unwrap();
+        let reader = FileReader::open(&empty).unwrap();
+        assert!(reader.entries().is_empty());
+        assert!(reader.decoders("cpu", 0, u64::MAX).unwrap().is_empty());
+
+        // a footer which claims more entries than it holds is rejected without reserving room
+        // for them all
+        let mut forged = empty.clone();
+        let len = forged.len();
+        let footer_offset = HEADER_LEN;
+        forged[footer_offset..footer_offset + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        let checksum = crc32(&forged[footer_offset..len - TRAILER_LEN]);
+        forged[len - 8..len - 4].copy_from_slice(&checksum.to_be_bytes());
+        assert_eq!(
+            FileReader::open(&forged).err().unwrap(),
+            Error::InvalidFooter
+        );
+
+        // a block which does not hold a stream fails to decode rather than panicking
+        let mut writer = FileWriter::new(Vec::new()).unwrap();
+        writer.append("cpu", 0, 10, 1, &[0xff]).unwrap();
+        let bytes = writer.finish().unwrap();
+        let reader = FileReader::open(&bytes).unwrap();
+        let mut decoders = reader.decoders("cpu", 0, 10).unwrap();
+        assert_eq!(
+            decoders[0].next(),
+            Some(Err(DecodeError::Truncated(Context::new(
+                0,
+                0,
+                FieldKind::StartTime
+            ))))
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use decode::{Context, Error, FieldKind};
+use stream;
+use stream::{Read, Write};
+use TimeUnit;
+
+/// MAGIC is the sequence of bytes, "tsz!", which begins every stream written with a `Header`
+pub const MAGIC: u32 = 0x7473_7a21;
+
+/// FORMAT_VERSION is the version of the stream format written by this crate
+pub const FORMAT_VERSION: u8 = 1;
+
+/// ValueCodec
+///
+/// ValueCodec identifies the scheme used to compress the values in a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum ValueCodec {
+    /// The XOR scheme from the Gorilla paper, which is written by `StdEncoder`.
+    #[default]
+    Gorilla,
+    /// The Chimp scheme, which is written by `ChimpEncoder::new`.
+    Chimp,
+    /// The Chimp scheme which also compares each value to the previous 128 values, which is
+    /// written by `ChimpEncoder::new_128`.
+    Chimp128,
+    /// The delta of delta scheme for integer values, which is written by `IntEncoder`.
+    Integer,
+}
+
+impl ValueCodec {
+    fn to_u8(self) -> u8 {
+        match self {
+            ValueCodec::Gorilla => 0,
+            ValueCodec::Chimp => 1,
+            ValueCodec::Chimp128 => 2,
+            ValueCodec::Integer => 3,
+        }
+    }
+
+    fn from_u8(code: u8) -> Option<ValueCodec> {
+        match code {
+            0 => Some(ValueCodec::Gorilla),
+            1 => Some(ValueCodec::Chimp),
+            2 => Some(ValueCodec::Chimp128),
+            3 => Some(ValueCodec::Integer),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn time_unit_to_u8(unit: TimeUnit) -> u8 {
+    match unit {
+        TimeUnit::Second => 0,
+        TimeUnit::Millisecond => 1,
+        TimeUnit::Microsecond => 2,
+        TimeUnit::Nanosecond => 3,
+    }
+}
+
+pub(crate) fn time_unit_from_u8(code: u8) -> Option<TimeUnit> {
+    match code {
+        0 => Some(TimeUnit::Second),
+        1 => Some(TimeUnit::Millisecond),
+        2 => Some(TimeUnit::Microsecond),
+        3 => Some(TimeUnit::Nanosecond),
+        _ => None,
+    }
+}
+
+/// Header
+///
+/// Header describes the parameters a stream was written with so that it can be decoded without
+/// knowing them in advance. When present it precedes the starting timestamp of the stream and
+/// consists of `MAGIC`, followed by a byte each for the format version, time unit and value codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Header {
+    version: u8,
+    time_unit: TimeUnit,
+    codec: ValueCodec,
+}
+
+impl Header {
+    /// LEN is the length, in bits, of an encoded Header
+    pub const LEN: u32 = 56;
+
+    /// new creates a new Header for the current format version
+    pub fn new(time_unit: TimeUnit, codec: ValueCodec) -> Self {
+        Header {
+            version: FORMAT_VERSION,
+            time_unit,
+            codec,
+        }
+    }
+
+    /// Get the format version of the stream.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Get the resolution of the timestamps in the stream.
+    pub fn time_unit(&self) -> TimeUnit {
+        self.time_unit
+    }
+
+    /// Get the scheme used to compress the values in the stream.
+    pub fn codec(&self) -> ValueCodec {
+        self.codec
+    }
+
+    /// write writes the header to `w`
+    pub fn write<W: Write>(&self, w: &mut W) -> Result<(), stream::Error> {
+        w.try_write_bits(u64::from(MAGIC), 32)?;
+        w.try_write_byte(self.version)?;
+        w.try_write_byte(time_unit_to_u8(self.time_unit))?;
+        w.try_write_byte(self.codec.to_u8())
+    }
+
+    /// read reads a header from `r`, validating its magic number and format version
+    pub fn read<R: Read>(r: &mut R) -> Result<Header, Error> {
+        // a stream which ends within the header is reported as truncated at the offset of the
+        // field being read
+        let truncated =
+            |offset| move |err| Error::at(err, Context::new(offset, 0, FieldKind::Header));
+
+        if r.read_bits(32).map_err(truncated(0))? != u64::from(MAGIC) {
+            return Err(Error::BadMagic);
+        }
+
+        let version = r.read_byte().map_err(truncated(32))?;
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let time_unit =
+            time_unit_from_u8(r.read_byte().map_err(truncated(40))?).ok_or(Error::InvalidHeader)?;
+        let codec = ValueCodec::from_u8(r.read_byte().map_err(truncated(48))?)
+            .ok_or(Error::InvalidHeader)?;
+
+        Ok(Header {
+            version,
+            time_unit,
+            codec,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Header, ValueCodec, FORMAT_VERSION};
+    use decode::{Context, Error, FieldKind};
+    use stream::{BufferedReader, BufferedWriter, Write};
+    use TimeUnit;
+
+    #[test]
+    fn write_and_read_header() {
+        let header = Header::new(TimeUnit::Millisecond, ValueCodec::Gorilla);
+
+        let mut w = BufferedWriter::new();
+        header.write(&mut w).unwrap();
+        let bytes = w.close();
+
+        assert_eq!(bytes[..], [0x74, 0x73, 0x7a, 0x21, FORMAT_VERSION, 1, 0]);
+
+        let mut r = BufferedReader::new(bytes);
+        assert_eq!(Header::read(&mut r).unwrap(), header);
+    }
+
+    #[test]
+    fn read_bad_magic() {
+        // a stream without a header starts with its initial timestamp
+        let bytes = vec![0, 0, 0, 0, 88, 89, 157, 151, 240, 0, 0, 0, 0];
+        let mut r = BufferedReader::new(bytes.into_boxed_slice());
+
+        assert_eq!(Header::read(&mut r).err().unwrap(), Error::BadMagic);
+    }
+
+    #[test]
+    fn read_unsupported_version() {
+        let bytes = vec![0x74, 0x73, 0x7a, 0x21, FORMAT_VERSION + 1, 0, 0];
+        let mut r = BufferedReader::new(bytes.into_boxed_slice());
+
+        assert_eq!(
+            Header::read(&mut r).err().unwrap(),
+            Error::UnsupportedVersion(FORMAT_VERSION + 1)
+        );
+    }
+
+    #[test]
+    fn read_invalid_header() {
+        let bytes = vec![0x74, 0x73, 0x7a, 0x21, FORMAT_VERSION, 9, 0];
+        let mut r = BufferedReader::new(bytes.into_boxed_slice());
+
+        assert_eq!(Header::read(&mut r).err().unwrap(), Error::InvalidHeader);
+
+        // a header cut short is truncated at the field which could not be read
+        let mut r = BufferedReader::new(vec![0x74, 0x73, 0x7a, 0x21, FORMAT_VERSION].into());
+        assert_eq!(
+            Header::read(&mut r).err().unwrap(),
+            Error::Truncated(Context::new(40, 0, FieldKind::Header))
+        );
+    }
+}
@@ -0,0 +1,139 @@
+/// IndexEntry
+///
+/// IndexEntry records the state of a `StdEncoder` immediately after it encoded a `DataPoint`, so
+/// that a `StdDecoder` can restore that state and resume decoding from the next `DataPoint`
+/// without decoding the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct IndexEntry {
+    offset: u64,          // bit offset of the next DataPoint in the stream
+    time: u64,            // time of the DataPoint
+    delta: u64,           // time delta of the DataPoint
+    value_bits: u64,      // value of the DataPoint as bits
+    leading_zeroes: u32,  // leading zeroes of the last xor stored
+    trailing_zeroes: u32, // trailing zeroes of the last xor stored
+}
+
+impl IndexEntry {
+    pub(crate) fn new(
+        offset: u64,
+        time: u64,
+        delta: u64,
+        value_bits: u64,
+        leading_zeroes: u32,
+        trailing_zeroes: u32,
+    ) -> Self {
+        IndexEntry {
+            offset,
+            time,
+            delta,
+            value_bits,
+            leading_zeroes,
+            trailing_zeroes,
+        }
+    }
+
+    /// Get the offset, in bits from the start of the stream, of the `DataPoint` after this entry.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the time of the `DataPoint` this entry was recorded after.
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Get the time delta of the `DataPoint` this entry was recorded after.
+    pub fn delta(&self) -> u64 {
+        self.delta
+    }
+
+    /// Get the value, as bits, of the `DataPoint` this entry was recorded after.
+    pub fn value_bits(&self) -> u64 {
+        self.value_bits
+    }
+
+    /// Get the number of leading zeroes in the last xor whose leading zeroes were stored.
+    pub fn leading_zeroes(&self) -> u32 {
+        self.leading_zeroes
+    }
+
+    /// Get the number of trailing zeroes in the last xor whose trailing zeroes were stored.
+    pub fn trailing_zeroes(&self) -> u32 {
+        self.trailing_zeroes
+    }
+}
+
+/// BlockIndex
+///
+/// BlockIndex is a sparse index of a stream written by a `StdEncoder`, with an `IndexEntry` for
+/// every `interval` `DataPoint`s. It is kept alongside the stream rather than in it, and its
+/// offsets are relative to the first bit the encoder's writer wrote, so the stream must be read
+/// from the same position for `StdDecoder::seek` to use it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct BlockIndex {
+    interval: u64,            // number of DataPoints between entries
+    start: u64,               // bit offset of the starting timestamp of the stream
+    entries: Vec<IndexEntry>, // entries in the order they were recorded
+}
+
+impl BlockIndex {
+    pub(crate) fn new(interval: u64, start: u64) -> Self {
+        BlockIndex {
+            interval,
+            start,
+            entries: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, entry: IndexEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Get the number of `DataPoint`s between entries.
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// Get the offset, in bits, of the starting timestamp of the stream.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Get the entries of the index, which are ordered by time.
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// find returns the last entry whose time is before `time`, which is the closest point to
+    /// resume decoding from to find the first `DataPoint` at or after `time`
+    pub fn find(&self, time: u64) -> Option<&IndexEntry> {
+        self.find_point(time).map(|(_, entry)| entry)
+    }
+
+    // find_point returns the entry `find` returns along with the index of the DataPoint after it
+    pub(crate) fn find_point(&self, time: u64) -> Option<(u64, &IndexEntry)> {
+        let i = self.entries.partition_point(|entry| entry.time < time);
+        i.checked_sub(1)
+            .map(|i| ((i as u64 + 1) * self.interval, &self.entries[i]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockIndex, IndexEntry};
+
+    #[test]
+    fn find_entry() {
+        let mut index = BlockIndex::new(2, 0);
+        for &time in &[10, 20, 20, 30] {
+            index.push(IndexEntry::new(time * 100, time, 10, 0, 64, 64));
+        }
+
+        assert_eq!(index.find(5), None);
+        assert_eq!(index.find(10), None);
+        assert_eq!(index.find(11).unwrap().offset(), 1000);
+        assert_eq!(index.find(20).unwrap().offset(), 1000);
+        assert_eq!(index.find(21).unwrap().time(), 20);
+        assert_eq!(index.find(100).unwrap().time(), 30);
+    }
+}
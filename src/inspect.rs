@@ -0,0 +1,572 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::iter::FusedIterator;
+
+use decode::{Context, Error, FieldKind};
+use header::{Header, ValueCodec};
+use stream::{Read, Seek};
+use {Bit, TimeUnit};
+
+/// XorCase
+///
+/// XorCase is how a value after the first is stored, which the control bits before it select.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XorCase {
+    /// The value is the same as the previous value, control bit 0.
+    Same,
+    /// The meaningful bits of the xor with the previous value fit in the window of leading and
+    /// trailing zeroes of the last xor which stored them, control bits 10.
+    ReuseWindow,
+    /// The xor is stored with a new window of leading zeroes and significant bits, control bits
+    /// 11.
+    NewWindow,
+}
+
+/// Field
+///
+/// Field is a field of a stream written by a `StdEncoder`, along with the value it decodes to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    /// The `Header` written by `StdEncoder::with_header`.
+    Header(Header),
+    /// The starting timestamp of the stream.
+    StartTime(u64),
+    /// The single zero bit which shows the stream holds at least one `DataPoint`.
+    FirstControl,
+    /// The delta between the starting timestamp and the time of the first `DataPoint`.
+    FirstDelta(u64),
+    /// The value of the first `DataPoint`, which is stored exactly.
+    FirstValue(f64),
+    /// The control bits which select the bucket of a delta of delta, from 0 for a delta of delta
+    /// of zero to 4 for the largest bucket.
+    TimestampControl(usize),
+    /// A delta of delta, and the time of the `DataPoint` it gives.
+    DeltaOfDelta(i64, u64),
+    /// The control bits which select how a value is stored.
+    ValueControl(XorCase),
+    /// The number of leading zeroes of a new window.
+    LeadingZeroes(u32),
+    /// The number of significant bits of a new window.
+    SignificantBits(u32),
+    /// The meaningful bits of the xor with the previous value, and the value they give.
+    Xor(f64),
+    /// The empty largest bucket which, after the control bits 1111, ends the stream.
+    EndMarker,
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Field::Header(ref header) => write!(
+                f,
+                "header version {} unit {:?} codec {:?}",
+                header.version(),
+                header.time_unit(),
+                header.codec()
+            ),
+            Field::StartTime(time) => write!(f, "start time {}", time),
+            Field::FirstControl => write!(f, "first control"),
+            Field::FirstDelta(delta) => write!(f, "first delta {}", delta),
+            Field::FirstValue(value) => write!(f, "first value {}", value),
+            Field::TimestampControl(bucket) => write!(f, "timestamp control bucket {}", bucket),
+            Field::DeltaOfDelta(dod, time) => write!(f, "delta of delta {} time {}", dod, time),
+            Field::ValueControl(case) => write!(f, "value control {:?}", case),
+            Field::LeadingZeroes(n) => write!(f, "leading zeroes {}", n),
+            Field::SignificantBits(n) => write!(f, "significant bits {}", n),
+            Field::Xor(value) => write!(f, "xor value {}", value),
+            Field::EndMarker => write!(f, "end marker"),
+        }
+    }
+}
+
+/// Event
+///
+/// Event is a single field of a stream, with the index of the `DataPoint` it belongs to, its bit
+/// offset from the start of the stream and the raw bits it was read from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Event {
+    point: u64,  // index of the DataPoint the field belongs to
+    offset: u64, // bit offset of the field
+    len: u32,    // length of the field in bits
+    bits: u64,   // raw bits of the field
+    field: Field,
+}
+
+impl Event {
+    /// Get the index of the `DataPoint` being read when the field was read, which is the number
+    /// of `DataPoint`s in the stream for the end marker.
+    pub fn point(&self) -> u64 {
+        self.point
+    }
+
+    /// Get the offset of the field in bits from the start of the stream.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the length of the field in bits.
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Check if the field has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the raw bits of the field, right aligned.
+    pub fn bits(&self) -> u64 {
+        self.bits
+    }
+
+    /// Get the field and the value it decodes to.
+    pub fn field(&self) -> Field {
+        self.field
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bits = if self.len == 0 {
+            String::from("-")
+        } else {
+            format!("{:0width$b}", self.bits, width = self.len as usize)
+        };
+
+        write!(
+            f,
+            "{:>8} {:>6} {:>3}  {}  {}",
+            self.offset, self.point, self.len, self.field, bits
+        )
+    }
+}
+
+/// Inspector
+///
+/// Inspector walks a stream written by a `StdEncoder` and yields an `Event` for each field it
+/// reads, the same way a `StdDecoder` reads them, to explain how a stream is compressed or where
+/// it is corrupt. After an error it yields the fields it read before the error, then the error,
+/// and then stops.
+#[derive(Debug)]
+pub struct Inspector<R: Read + Seek> {
+    unit: TimeUnit,
+    header: bool,  // is a header still to be read
+    started: bool, // has the starting timestamp been read
+
+    point: u64, // index of the next DataPoint
+    time: u64,
+    delta: u64,
+    value_bits: u64,
+    leading_zeroes: u32,
+    trailing_zeroes: u32,
+
+    events: VecDeque<Event>,
+    error: Option<Error>,
+    done: bool,
+
+    r: R,
+}
+
+impl<R> Inspector<R>
+where
+    R: Read + Seek,
+{
+    /// new creates a new Inspector which will read a stream whose timestamps are in seconds from
+    /// r
+    pub fn new(r: R) -> Self {
+        Inspector::with_time_unit(r, TimeUnit::Second)
+    }
+
+    /// with_time_unit creates a new Inspector which will read a stream whose timestamps have a
+    /// resolution of `unit` from r
+    pub fn with_time_unit(r: R, unit: TimeUnit) -> Self {
+        Inspector::create(r, unit, false)
+    }
+
+    /// with_header creates a new Inspector which will read a stream which begins with the
+    /// `Header` written by `StdEncoder::with_header` from r, the time unit is taken from the
+    /// header
+    pub fn with_header(r: R) -> Self {
+        Inspector::create(r, TimeUnit::Second, true)
+    }
+
+    fn create(r: R, unit: TimeUnit, header: bool) -> Self {
+        Inspector {
+            unit,
+            header,
+            started: false,
+            point: 0,
+            time: 0,
+            delta: 0,
+            value_bits: 0,
+            leading_zeroes: 64, // 64 is an initial sentinel value, as in the encoder
+            trailing_zeroes: 64, // 64 is an initial sentinel value
+            events: VecDeque::new(),
+            error: None,
+            done: false,
+            r,
+        }
+    }
+
+    // read reads a field of `len` bits, recording an event for it with the field `f` returns
+    fn read<F>(&mut self, len: u32, kind: FieldKind, f: F) -> Result<u64, Error>
+    where
+        F: FnOnce(u64) -> Field,
+    {
+        let offset = self.r.bit_position();
+        let bits = self
+            .r
+            .read_bits(len)
+            .map_err(|err| Error::at(err, self.context(offset, kind)))?;
+        self.push(offset, len, bits, f(bits));
+        Ok(bits)
+    }
+
+    // peak returns the next `len` bits of the stream, which are part of a field of `kind`
+    fn peak(&mut self, len: u32, kind: FieldKind) -> Result<u64, Error> {
+        let offset = self.r.bit_position();
+        self.r
+            .peak_bits(len)
+            .map_err(|err| Error::at(err, self.context(offset, kind)))
+    }
+
+    fn push(&mut self, offset: u64, len: u32, bits: u64, field: Field) {
+        self.events.push_back(Event {
+            point: self.point,
+            offset,
+            len,
+            bits,
+            field,
+        });
+    }
+
+    // context describes a field of `kind` at `offset` in the DataPoint being read
+    fn context(&self, offset: u64, kind: FieldKind) -> Context {
+        Context::new(offset, self.point, kind)
+    }
+
+    // corrupt returns the error for a field of `kind` at `offset` which holds bits that an encoder
+    // never writes
+    fn corrupt(&self, offset: u64, kind: FieldKind) -> Error {
+        Error::Corrupt(self.context(offset, kind))
+    }
+
+    // step reads the fields of the next part of the stream, which is the header, the starting
+    // timestamp or a DataPoint
+    fn step(&mut self) -> Result<(), Error> {
+        if self.header {
+            self.header = false;
+
+            let offset = self.r.bit_position();
+            let bits = self.peak(Header::LEN, FieldKind::Header)?;
+            let header = Header::read(&mut self.r)?;
+            self.push(offset, Header::LEN, bits, Field::Header(header));
+
+            if header.codec() != ValueCodec::Gorilla {
+                return Err(Error::UnexpectedCodec(header.codec()));
+            }
+            self.unit = header.time_unit();
+            return Ok(());
+        }
+
+        if !self.started {
+            self.started = true;
+            self.time = self.read(64, FieldKind::StartTime, Field::StartTime)?;
+            return Ok(());
+        }
+
+        if self.point == 0 {
+            self.read_first()
+        } else {
+            self.read_next()
+        }
+    }
+
+    fn read_first(&mut self) -> Result<(), Error> {
+        if self.peak(1, FieldKind::FirstControl)? == 1 {
+            let control_bits = self.read(4, FieldKind::TimestampControl, |bits| {
+                Field::TimestampControl(leading_ones(bits, 4))
+            })?;
+            return self.read_end_marker(control_bits == 0b1111);
+        }
+
+        self.read(1, FieldKind::FirstControl, |_| Field::FirstControl)?;
+
+        let offset = self.r.bit_position();
+        let delta = self.read(
+            self.unit.first_delta_len(),
+            FieldKind::FirstDelta,
+            Field::FirstDelta,
+        )?;
+        self.delta = delta;
+        self.time = self
+            .time
+            .checked_add(delta)
+            .ok_or_else(|| self.corrupt(offset, FieldKind::FirstDelta))?;
+
+        self.value_bits = self.read(64, FieldKind::FirstValue, |bits| {
+            Field::FirstValue(f64::from_bits(bits))
+        })?;
+        self.point += 1;
+
+        Ok(())
+    }
+
+    fn read_next(&mut self) -> Result<(), Error> {
+        // the control bits are read one at a time, up to the first zero
+        let offset = self.r.bit_position();
+        let mut bucket = 0;
+        let mut bits = 0;
+        let mut len = 0;
+        while bucket < 4 {
+            let context = self.context(offset + len, FieldKind::TimestampControl);
+            let bit = self.r.read_bit().map_err(|err| Error::at(err, context))?;
+            len += 1;
+            bits = bits << 1 | u64::from(bit == Bit::One);
+            if bit == Bit::Zero {
+                break;
+            }
+            bucket += 1;
+        }
+        self.push(offset, len as u32, bits, Field::TimestampControl(bucket));
+
+        if bucket == 0 {
+            self.time = self
+                .time
+                .checked_add(self.delta)
+                .ok_or_else(|| self.corrupt(offset, FieldKind::TimestampControl))?;
+        } else {
+            let size = self.unit.bucket_lens()[bucket - 1];
+            if bucket == 4 && self.peak(size, FieldKind::DeltaOfDelta)? == 0 {
+                return self.read_end_marker(true);
+            }
+
+            let offset = self.r.bit_position();
+            let raw = self
+                .r
+                .read_bits(size)
+                .map_err(|err| Error::at(err, self.context(offset, FieldKind::DeltaOfDelta)))?;
+            let mut dod = raw;
+            if size < 64 && dod > (1 << (size - 1)) {
+                dod |= u64::MAX << size;
+            }
+
+            self.delta = self.delta.wrapping_add(dod);
+            self.time = self
+                .time
+                .checked_add(self.delta)
+                .ok_or_else(|| self.corrupt(offset, FieldKind::DeltaOfDelta))?;
+            self.push(
+                offset,
+                size,
+                raw,
+                Field::DeltaOfDelta(dod as i64, self.time),
+            );
+        }
+
+        self.read_next_value()?;
+        self.point += 1;
+
+        Ok(())
+    }
+
+    fn read_next_value(&mut self) -> Result<(), Error> {
+        let offset = self.r.bit_position();
+        let context = self.context(offset, FieldKind::ValueControl);
+        if self.r.read_bit().map_err(|err| Error::at(err, context))? == Bit::Zero {
+            self.push(offset, 1, 0, Field::ValueControl(XorCase::Same));
+            return Ok(());
+        }
+
+        let context = self.context(offset + 1, FieldKind::ValueControl);
+        let new_window = self.r.read_bit().map_err(|err| Error::at(err, context))? == Bit::One;
+        if new_window {
+            self.push(offset, 2, 0b11, Field::ValueControl(XorCase::NewWindow));
+            let leading_zeroes = self.read(6, FieldKind::LeadingZeroes, |bits| {
+                Field::LeadingZeroes(bits as u32)
+            })? as u32;
+
+            let offset = self.r.bit_position();
+            let significant_bits = self.read(6, FieldKind::SignificantBits, |bits| {
+                Field::SignificantBits(bits as u32 + 1)
+            })? as u32
+                + 1;
+            if leading_zeroes + significant_bits > 64 {
+                return Err(self.corrupt(offset, FieldKind::SignificantBits));
+            }
+
+            self.leading_zeroes = leading_zeroes;
+            self.trailing_zeroes = 64 - leading_zeroes - significant_bits;
+        } else {
+            self.push(offset, 2, 0b10, Field::ValueControl(XorCase::ReuseWindow));
+            if self.leading_zeroes + self.trailing_zeroes >= 64 {
+                return Err(self.corrupt(offset, FieldKind::ValueControl));
+            }
+        }
+
+        let offset = self.r.bit_position();
+        let size = 64 - self.leading_zeroes - self.trailing_zeroes;
+        let (value_bits, trailing_zeroes) = (self.value_bits, self.trailing_zeroes);
+        let xor = self.read(size, FieldKind::Xor, |bits| {
+            Field::Xor(f64::from_bits(value_bits ^ (bits << trailing_zeroes)))
+        })?;
+        if xor == 0 || (new_window && (xor >> (size - 1) == 0 || xor & 1 == 0)) {
+            return Err(self.corrupt(offset, FieldKind::Xor));
+        }
+        self.value_bits ^= xor << self.trailing_zeroes;
+
+        Ok(())
+    }
+
+    // read_end_marker reads the largest bucket after the control bits 1111, which must be empty
+    fn read_end_marker(&mut self, control_bits: bool) -> Result<(), Error> {
+        let size = self.unit.bucket_lens()[3];
+        let offset = self.r.bit_position();
+        let bits = self
+            .r
+            .read_bits(size)
+            .map_err(|err| Error::at(err, self.context(offset, FieldKind::EndMarker)))?;
+        if !control_bits || bits != 0 {
+            return Err(self.corrupt(offset, FieldKind::EndMarker));
+        }
+
+        self.push(offset, size, bits, Field::EndMarker);
+        self.done = true;
+
+        Ok(())
+    }
+}
+
+// leading_ones returns the number of leading ones in the `len` bits of `bits`
+fn leading_ones(bits: u64, len: u32) -> usize {
+    (bits << (64 - len)).leading_ones() as usize
+}
+
+impl<R> Iterator for Inspector<R>
+where
+    R: Read + Seek,
+{
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some(Ok(event));
+            }
+            if let Some(err) = self.error.take() {
+                return Some(Err(err));
+            }
+            if self.done {
+                return None;
+            }
+
+            if let Err(err) = self.step() {
+                self.error = Some(err);
+                self.done = true;
+            }
+        }
+    }
+}
+
+impl<R> FusedIterator for Inspector<R> where R: Read + Seek {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, Field, Inspector, XorCase};
+    use decode::Error;
+    use stream::{BufferedWriter, SliceReader};
+    use {DataPoint, Encode, StdDecoder, StdEncoder, TimeUnit};
+
+    fn inspect(bytes: &[u8]) -> Vec<Result<Event, Error>> {
+        Inspector::new(SliceReader::new(bytes)).collect()
+    }
+
+    #[test]
+    fn inspect_fields() {
+        let start = 1482892260;
+        let mut e = StdEncoder::new(start, BufferedWriter::new());
+        e.encode(DataPoint::new(1482892270, 1.76));
+        e.encode(DataPoint::new(1482892280, 1.76));
+        e.encode(DataPoint::new(1482892288, 7.95));
+        e.encode(DataPoint::new(1482892292, 7.78));
+        let bytes = e.close();
+
+        let events: Vec<Event> = inspect(&bytes).into_iter().map(|e| e.unwrap()).collect();
+        let fields: Vec<Field> = events.iter().map(|event| event.field()).collect();
+        assert_eq!(
+            fields,
+            vec![
+                Field::StartTime(start),
+                Field::FirstControl,
+                Field::FirstDelta(10),
+                Field::FirstValue(1.76),
+                Field::TimestampControl(0),
+                Field::ValueControl(XorCase::Same),
+                Field::TimestampControl(1),
+                Field::DeltaOfDelta(-2, 1482892288),
+                Field::ValueControl(XorCase::NewWindow),
+                Field::LeadingZeroes(1),
+                Field::SignificantBits(61),
+                Field::Xor(7.95),
+                Field::TimestampControl(1),
+                Field::DeltaOfDelta(-4, 1482892292),
+                Field::ValueControl(XorCase::NewWindow),
+                Field::LeadingZeroes(16),
+                Field::SignificantBits(47),
+                Field::Xor(7.78),
+                Field::TimestampControl(4),
+                Field::EndMarker,
+            ]
+        );
+
+        // the events cover the stream without gaps or overlaps
+        let mut offset = 0;
+        for event in &events {
+            assert_eq!(event.offset(), offset);
+            offset += u64::from(event.len());
+        }
+        assert_eq!(offset.div_ceil(8), bytes.len() as u64);
+
+        assert_eq!(events[3].point(), 0);
+        assert_eq!(events[5].point(), 1);
+        assert_eq!(events[6].bits(), 0b10);
+        assert_eq!(events[7].bits(), 0b1111110);
+    }
+
+    #[test]
+    fn inspect_corrupt_stream() {
+        let mut e = StdEncoder::with_header(1000, TimeUnit::Millisecond, BufferedWriter::new());
+        for i in 0..10u64 {
+            e.encode(DataPoint::new(1000 + i * 100, i as f64));
+        }
+        let bytes = e.close();
+
+        // a complete stream ends with an end marker
+        let events: Vec<_> = Inspector::with_header(SliceReader::new(&bytes)).collect();
+        assert!(matches!(events[0], Ok(ref event) if event.offset() == 0 && event.len() == 56));
+        assert_eq!(
+            events.last().unwrap().as_ref().unwrap().field(),
+            Field::EndMarker
+        );
+
+        // a truncated stream yields the fields before the point where it was cut, then an error
+        let cut = bytes.len() * 3 / 4;
+        let events: Vec<_> = Inspector::with_header(SliceReader::new(&bytes[..cut])).collect();
+        let (last, complete) = events.split_last().unwrap();
+        let reached = complete.last().unwrap().as_ref().unwrap();
+        let end = reached.offset() + u64::from(reached.len());
+        assert!(end <= cut as u64 * 8);
+        assert!(reached.point() > 0);
+        match *last {
+            Err(Error::Truncated(context)) => assert_eq!(context.offset(), end),
+            ref last => panic!("unexpected result {:?}", last),
+        }
+
+        // the decoder reports the same error
+        let decoded: Result<Vec<_>, Error> =
+            StdDecoder::with_header(SliceReader::new(&bytes[..cut]))
+                .unwrap()
+                .collect();
+        assert_eq!(Err(decoded.err().unwrap()), *last);
+    }
+}
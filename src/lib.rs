@@ -33,7 +33,7 @@
 //! extern crate tsz;
 //!
 //! use std::vec::Vec;
-//! use tsz::{DataPoint, Encode, Decode, StdEncoder, StdDecoder};
+//! use tsz::{DataPoint, Encode, StdEncoder, StdDecoder};
 //! use tsz::stream::{BufferedReader, BufferedWriter};
 //! use tsz::decode::Error;
 //!
@@ -77,27 +77,10 @@
 //!
 //!     let bytes = encoder.close();
 //!     let r = BufferedReader::new(bytes);
-//!     let mut decoder = StdDecoder::new(r);
+//!     let decoder = StdDecoder::new(r);
 //!
-//!     let mut expected_datapoints = Vec::new();
-//!
-//!     let mut done = false;
-//!     loop {
-//!         if done {
-//!             break;
-//!         }
-//!
-//!         match decoder.next() {
-//!             Ok(dp) => expected_datapoints.push(dp),
-//!             Err(err) => {
-//!                 if err == Error::EndOfStream {
-//!                     done = true;
-//!                 } else {
-//!                     panic!("Received an error from decoder: {:?}", err);
-//!                 }
-//!             }
-//!         };
-//!     }
+//!     let expected_datapoints: Result<Vec<DataPoint>, Error> = decoder.collect();
+//!     let expected_datapoints = expected_datapoints.expect("Received an error from decoder");
 //!
 //!     println!("actual datapoints: {:?}", actual_datapoints);
 //!     println!("expected datapoints: {:?}", expected_datapoints);
@@ -185,16 +168,106 @@ impl PartialOrd for DataPoint {
     }
 }
 
+/// IntDataPoint
+///
+/// Struct used to represent a single datapoint whose value is an integer, such as a counter or a
+/// queue depth. Consists of a time and value.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Deserialize, serde::Serialize,
+)]
+pub struct IntDataPoint {
+    time: u64,
+    value: i64,
+}
+
+impl IntDataPoint {
+    /// Create a new IntDataPoint from a time and value.
+    pub fn new(time: u64, value: i64) -> Self {
+        IntDataPoint { time, value }
+    }
+
+    /// Get the time for this IntDataPoint.
+    pub fn get_time(&self) -> u64 {
+        self.time
+    }
+
+    /// Get the value for this IntDataPoint.
+    pub fn get_value(&self) -> i64 {
+        self.value
+    }
+}
+
+/// TimeUnit
+///
+/// TimeUnit is the resolution of the timestamps in a stream. It determines how many bits are used
+/// to store the first delta and the delta of delta in each of the variable length buckets, so
+/// that timestamps with a finer resolution than seconds still compress well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum TimeUnit {
+    #[default]
+    Second,
+    Millisecond,
+    Microsecond,
+    Nanosecond,
+}
+
+impl TimeUnit {
+    /// Get the number of bits used to store the delta between the start of the stream and the
+    /// first `DataPoint`, which is enough to span just over 4 hours in every unit.
+    pub fn first_delta_len(self) -> u32 {
+        match self {
+            TimeUnit::Second => 14,
+            TimeUnit::Millisecond => 24,
+            TimeUnit::Microsecond => 34,
+            TimeUnit::Nanosecond => 44,
+        }
+    }
+
+    /// Get the number of bits used to store the delta of delta in each bucket, in the order of
+    /// their control bits 10, 110, 1110 and 1111.
+    pub fn bucket_lens(self) -> [u32; 4] {
+        match self {
+            TimeUnit::Second => [7, 9, 12, 32],
+            TimeUnit::Millisecond => [10, 14, 20, 32],
+            TimeUnit::Microsecond => [14, 20, 27, 48],
+            TimeUnit::Nanosecond => [17, 24, 34, 64],
+        }
+    }
+}
+
 pub mod stream;
 
+pub mod header;
+
+pub mod index;
+
 pub mod encode;
-pub use self::encode::std_encoder::StdEncoder;
+pub use self::encode::chimp_encoder::ChimpEncoder;
+pub use self::encode::int_encoder::IntEncoder;
+pub use self::encode::std_encoder::{EncoderState, StdEncoder};
 pub use self::encode::Encode;
 
 pub mod decode;
+pub use self::decode::chimp_decoder::ChimpDecoder;
+pub use self::decode::int_decoder::IntDecoder;
 pub use self::decode::std_decoder::StdDecoder;
+pub use self::decode::timestamp_decoder::TimestampDecoder;
 pub use self::decode::Decode;
 
+pub mod store;
+
+pub mod file;
+
+pub mod wal;
+
+pub mod compact;
+
+pub mod agg;
+
+pub mod rollup;
+
+pub mod inspect;
+
 #[cfg(test)]
 mod tests {
     extern crate test_case;
@@ -202,11 +275,12 @@ mod tests {
     use std::vec::Vec;
 
     use super::decode::Error;
+    use super::encode;
     use super::stream::{BufferedReader, BufferedWriter};
-    use super::{DataPoint, Decode, Encode, StdDecoder, StdEncoder};
+    use super::{ChimpDecoder, ChimpEncoder, DataPoint, Encode, StdDecoder, StdEncoder, TimeUnit};
 
     // A representative time series.
-    const DATA_1: &'static str = "1482892270,1.76
+    const DATA_1: &str = "1482892270,1.76
 1482892280,7.78
 1482892288,7.95
 1482892292,5.53
@@ -225,7 +299,7 @@ mod tests {
 ";
 
     // A time series where there is relatively large variation in times.
-    const DATA_2: &'static str = "0,0.0
+    const DATA_2: &str = "0,0.0
 1,0.0
 5000,0.0";
 
@@ -251,29 +325,131 @@ mod tests {
 
         let bytes = encoder.close();
         let r = BufferedReader::new(bytes);
-        let mut decoder = StdDecoder::new(r);
+        let decoder = StdDecoder::new(r);
 
-        let mut new_datapoints = Vec::new();
+        let new_datapoints: Result<Vec<DataPoint>, Error> = decoder.collect();
 
-        let mut done = false;
-        loop {
-            if done {
-                break;
-            }
+        assert_eq!(original_datapoints, new_datapoints.unwrap());
+    }
+
+    #[test_case::test_case(DATA_1, false ; "a representative time series with chimp")]
+    #[test_case::test_case(DATA_1, true ; "a representative time series with chimp128")]
+    #[test_case::test_case(DATA_2, false ; "a time series with relatively large variation in times with chimp")]
+    #[test_case::test_case(DATA_2, true ; "a time series with relatively large variation in times with chimp128")]
+    fn chimp_test(data: &str, window: bool) {
+        let w = BufferedWriter::new();
+        let mut encoder = if window {
+            ChimpEncoder::new_128(0, w)
+        } else {
+            ChimpEncoder::new(0, w)
+        };
+
+        let mut original_datapoints = Vec::new();
+
+        for line in data.lines() {
+            let substrings: Vec<&str> = line.split(",").collect();
+            let t = substrings[0].parse::<u64>().unwrap() % 10_000;
+            let v = substrings[1].parse::<f64>().unwrap();
+            let dp = DataPoint::new(t, v);
+            original_datapoints.push(dp);
+        }
+
+        for dp in &original_datapoints {
+            encoder.try_encode(*dp).unwrap();
+        }
+
+        let bytes = encoder.close();
+        let r = BufferedReader::new(bytes);
+        let decoder = if window {
+            ChimpDecoder::new_128(r)
+        } else {
+            ChimpDecoder::new(r)
+        };
+
+        let new_datapoints: Result<Vec<DataPoint>, Error> = decoder.collect();
+
+        assert_eq!(original_datapoints, new_datapoints.unwrap());
+    }
+
+    #[test_case::test_case(TimeUnit::Second, 1 ; "seconds")]
+    #[test_case::test_case(TimeUnit::Millisecond, 1_000 ; "milliseconds")]
+    #[test_case::test_case(TimeUnit::Microsecond, 1_000_000 ; "microseconds")]
+    #[test_case::test_case(TimeUnit::Nanosecond, 1_000_000_000 ; "nanoseconds")]
+    fn time_unit_test(unit: TimeUnit, scale: u64) {
+        let start_time = 1482892260 * scale;
+        let w = BufferedWriter::new();
+        let mut encoder = StdEncoder::with_time_unit(start_time, unit, w);
+
+        // scale DATA_1 to the unit, adding some jitter below one second
+        let mut original_datapoints = Vec::new();
+        for (i, line) in DATA_1.lines().enumerate() {
+            let substrings: Vec<&str> = line.split(",").collect();
+            let t = substrings[0].parse::<u64>().unwrap() * scale + (i as u64 * 7919) % scale;
+            let v = substrings[1].parse::<f64>().unwrap();
+            original_datapoints.push(DataPoint::new(t, v));
+        }
+
+        for dp in &original_datapoints {
+            encoder.try_encode(*dp).unwrap();
+        }
+
+        let bytes = encoder.close();
+        let r = BufferedReader::new(bytes);
+        let decoder = StdDecoder::with_time_unit(r, unit);
+
+        let new_datapoints: Result<Vec<DataPoint>, Error> = decoder.collect();
+
+        assert_eq!(original_datapoints, new_datapoints.unwrap());
 
-            match decoder.next() {
-                Ok(dp) => new_datapoints.push(dp),
-                Err(err) => {
-                    if err == Error::EndOfStream {
-                        done = true;
-                    } else {
-                        panic!("Received an error from decoder: {:?}", err);
-                    }
-                }
-            };
+        // every unit can store a first delta of 4 hours, which overflows a stream in seconds when
+        // the timestamps are finer than seconds
+        let dp = DataPoint::new(start_time + 4 * 3600 * scale, 0.0);
+
+        let w = BufferedWriter::new();
+        let mut encoder = StdEncoder::with_time_unit(start_time, unit, w);
+        assert!(encoder.try_encode(dp).is_ok());
+
+        if unit != TimeUnit::Second {
+            let w = BufferedWriter::new();
+            let mut encoder = StdEncoder::new(start_time, w);
+            assert_eq!(
+                encoder.try_encode(dp).err().unwrap(),
+                encode::Error::FirstDeltaTooLarge
+            );
         }
+    }
+
+    #[test]
+    fn header_test() {
+        let start_time = 1482892260000;
+        let w = BufferedWriter::new();
+        let mut encoder = StdEncoder::with_header(start_time, TimeUnit::Millisecond, w);
+
+        let original_datapoints = vec![
+            DataPoint::new(start_time + 9_998, 1.76),
+            DataPoint::new(start_time + 20_003, 7.78),
+            DataPoint::new(start_time + 28_000, 7.95),
+        ];
+
+        for dp in &original_datapoints {
+            encoder.try_encode(*dp).unwrap();
+        }
+
+        // the decoder learns the time unit from the header
+        let bytes = encoder.close();
+        let r = BufferedReader::new(bytes);
+        let decoder = StdDecoder::with_header(r).unwrap();
+
+        let new_datapoints: Result<Vec<DataPoint>, Error> = decoder.collect();
+
+        assert_eq!(original_datapoints, new_datapoints.unwrap());
+
+        // streams without a header are rejected
+        let w = BufferedWriter::new();
+        let encoder = StdEncoder::new(start_time, w);
 
-        assert_eq!(original_datapoints, new_datapoints);
+        let r = BufferedReader::new(encoder.close());
+        assert_eq!(StdDecoder::with_header(r).err().unwrap(), Error::BadMagic);
     }
 
     #[test]
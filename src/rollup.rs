@@ -0,0 +1,378 @@
+use std::{error, fmt};
+
+use agg::BlockSummary;
+use decode;
+use encode;
+use stream::BufferedWriter;
+use {DataPoint, Encode, StdEncoder, TimeUnit};
+
+/// Error
+///
+/// Error encapsulates the potential errors that can be encountered when rolling up a stream
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The stream being rolled up could not be decoded.
+    Decode(decode::Error),
+    /// A `DataPoint` is out of order, or the rolled up `DataPoint`s could not be encoded.
+    Encode(encode::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Decode(ref err) => write!(f, "Decode error: {}", err),
+            Error::Encode(ref err) => write!(f, "Encode error: {}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Decode(ref err) => Some(err),
+            Error::Encode(ref err) => Some(err),
+        }
+    }
+}
+
+impl From<decode::Error> for Error {
+    fn from(err: decode::Error) -> Error {
+        Error::Decode(err)
+    }
+}
+
+impl From<encode::Error> for Error {
+    fn from(err: encode::Error) -> Error {
+        Error::Encode(err)
+    }
+}
+
+/// Aggregate
+///
+/// Aggregate is a value computed for each bucket of a rollup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Aggregate {
+    /// The mean of the values in the bucket.
+    Avg,
+    /// The smallest value in the bucket.
+    Min,
+    /// The largest value in the bucket.
+    Max,
+    /// The sum of the values in the bucket.
+    Sum,
+    /// The number of `DataPoint`s in the bucket.
+    Count,
+    /// The value of the latest `DataPoint` in the bucket.
+    Last,
+}
+
+impl Aggregate {
+    /// ALL is every aggregate, in the order of their streams in `Rollups`
+    pub const ALL: [Aggregate; 6] = [
+        Aggregate::Avg,
+        Aggregate::Min,
+        Aggregate::Max,
+        Aggregate::Sum,
+        Aggregate::Count,
+        Aggregate::Last,
+    ];
+
+    /// value returns the aggregate of the `DataPoint`s summarized by `summary`, which is NaN for
+    /// every aggregate other than the sum and count of an empty summary
+    pub fn value(self, summary: &BlockSummary) -> f64 {
+        match self {
+            Aggregate::Avg => summary.mean().unwrap_or(f64::NAN),
+            Aggregate::Min => summary.min().unwrap_or(f64::NAN),
+            Aggregate::Max => summary.max().unwrap_or(f64::NAN),
+            Aggregate::Sum => summary.sum(),
+            Aggregate::Count => summary.count() as f64,
+            Aggregate::Last => summary.last().map_or(f64::NAN, |dp| dp.get_value()),
+        }
+    }
+
+    fn position(self) -> usize {
+        match self {
+            Aggregate::Avg => 0,
+            Aggregate::Min => 1,
+            Aggregate::Max => 2,
+            Aggregate::Sum => 3,
+            Aggregate::Count => 4,
+            Aggregate::Last => 5,
+        }
+    }
+}
+
+/// Alignment
+///
+/// Alignment decides where the buckets of a rollup begin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    /// Buckets begin at multiples of their width, so rollups of different streams line up.
+    Epoch,
+    /// Buckets begin at the start of the rollup and every multiple of their width after it.
+    Start,
+}
+
+/// Rollups
+///
+/// Rollups holds the streams written by a `Rollup`, one for each `Aggregate`, which can be
+/// decoded by a `StdDecoder` with the time unit of the rollup.
+#[derive(Debug, Clone)]
+pub struct Rollups {
+    streams: Vec<Box<[u8]>>,
+}
+
+impl Rollups {
+    /// Get the stream of the values of `aggregate` for each bucket.
+    pub fn get(&self, aggregate: Aggregate) -> &[u8] {
+        &self.streams[aggregate.position()]
+    }
+}
+
+/// Rollup
+///
+/// Rollup downsamples `DataPoint`s into buckets of a fixed width and encodes an aggregate of each
+/// bucket into a new stream for each `Aggregate`, to keep coarser resolution copies of a series
+/// for longer than the raw `DataPoint`s. Each rolled up `DataPoint` is at the start of its bucket,
+/// except for a first bucket which begins before the start of the rollup, which is at the start
+/// of the rollup instead.
+///
+/// The streams start at the first bucket written rather than at the start of the rollup, so a
+/// rollup may start long before its first `DataPoint`, such as at the start of a retention period.
+///
+/// A bucket is only written once a `DataPoint` after it is pushed or the rollup is closed, so the
+/// blocks of a series can be pushed one after another and a bucket which spans the edge of two
+/// blocks is aggregated over both. A bucket left open when the rollup is closed is written with
+/// the `DataPoint`s it holds so far, its `Aggregate::Count` tells how complete it is.
+#[derive(Debug)]
+pub struct Rollup {
+    start: u64,
+    width: u64,
+    alignment: Alignment,
+    unit: TimeUnit,
+    bucket: Option<(u64, BlockSummary)>, // start and summary of the current bucket
+    // one for each Aggregate in the order of ALL, created when the first bucket is flushed
+    encoders: Vec<StdEncoder<BufferedWriter>>,
+}
+
+impl Rollup {
+    /// new creates a new Rollup for timestamps in seconds of the `DataPoint`s at or after `start`,
+    /// whose buckets are `width` seconds wide. A width of zero is treated as one.
+    pub fn new(start: u64, width: u64, alignment: Alignment) -> Self {
+        Rollup::with_time_unit(start, width, alignment, TimeUnit::Second)
+    }
+
+    /// with_time_unit creates a new Rollup like `new`, for timestamps with a resolution of `unit`
+    /// whose buckets are `width` units of time wide
+    pub fn with_time_unit(start: u64, width: u64, alignment: Alignment, unit: TimeUnit) -> Self {
+        Rollup {
+            start,
+            width: width.max(1),
+            alignment,
+            unit,
+            bucket: None,
+            encoders: Vec::new(),
+        }
+    }
+
+    /// push adds `dp` to its bucket, writing the previous bucket if `dp` is after it.
+    /// `DataPoint`s must not be pushed before the start of the rollup or before the bucket of the
+    /// previous `DataPoint`. Only the order of buckets is checked, so `DataPoint`s which are out
+    /// of order within the current bucket are accepted silently, and `Aggregate::Last` is the
+    /// value of the latest of them rather than the one pushed last.
+    pub fn push(&mut self, dp: DataPoint) -> Result<(), Error> {
+        let time = dp.get_time();
+        if time < self.start {
+            return Err(Error::Encode(encode::Error::OutOfOrder));
+        }
+
+        let offset = match self.alignment {
+            Alignment::Epoch => 0,
+            Alignment::Start => self.start % self.width,
+        };
+        let bucket = time - (time - offset) % self.width;
+
+        match self.bucket {
+            Some((current, _)) if bucket < current => {
+                return Err(Error::Encode(encode::Error::OutOfOrder));
+            }
+            Some((current, _)) if bucket > current => self.flush()?,
+            _ => {}
+        }
+
+        self.bucket
+            .get_or_insert_with(|| (bucket, BlockSummary::new()))
+            .1
+            .push(dp);
+
+        Ok(())
+    }
+
+    /// consume pushes every `DataPoint` from `decoder`
+    pub fn consume<I>(&mut self, decoder: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = Result<DataPoint, decode::Error>>,
+    {
+        for dp in decoder {
+            self.push(dp?)?;
+        }
+
+        Ok(())
+    }
+
+    /// close writes the current bucket, if any, and closes the stream of each aggregate
+    pub fn close(mut self) -> Result<Rollups, Error> {
+        self.flush()?;
+        if self.encoders.is_empty() {
+            self.create_encoders(self.start);
+        }
+
+        let mut streams = Vec::with_capacity(self.encoders.len());
+        for encoder in self.encoders {
+            streams.push(encoder.try_close()?);
+        }
+
+        Ok(Rollups { streams })
+    }
+
+    // flush writes the aggregates of the current bucket
+    fn flush(&mut self) -> Result<(), Error> {
+        if let Some((bucket, summary)) = self.bucket.take() {
+            let time = bucket.max(self.start);
+            if self.encoders.is_empty() {
+                self.create_encoders(time);
+            }
+
+            for (aggregate, encoder) in Aggregate::ALL.iter().zip(&mut self.encoders) {
+                encoder.try_encode(DataPoint::new(time, aggregate.value(&summary)))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // create_encoders creates the encoder of each aggregate, whose streams start at `start`
+    fn create_encoders(&mut self, start: u64) {
+        self.encoders = Aggregate::ALL
+            .iter()
+            .map(|_| StdEncoder::with_time_unit(start, self.unit, BufferedWriter::new()))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Aggregate, Alignment, Rollup};
+    use decode::decode_all;
+    use stream::{BufferedReader, BufferedWriter};
+    use {DataPoint, Encode, StdDecoder, StdEncoder};
+
+    // block encodes a DataPoint every 10 seconds from `from` until `to`, whose value is its index
+    // in the block
+    fn block(start: u64, from: u64, to: u64) -> Box<[u8]> {
+        let mut e = StdEncoder::new(start, BufferedWriter::new());
+        for (i, time) in (from..to).step_by(10).enumerate() {
+            e.encode(DataPoint::new(time, i as f64));
+        }
+        e.close()
+    }
+
+    fn values(bytes: &[u8]) -> Vec<(u64, f64)> {
+        decode_all(bytes)
+            .unwrap()
+            .into_iter()
+            .map(|dp| (dp.get_time(), dp.get_value()))
+            .collect()
+    }
+
+    #[test]
+    fn rollup_epoch_aligned() {
+        // two blocks of 10 second data from 00:00:30 to 00:03:30, split at 00:01:45
+        let mut rollup = Rollup::new(30, 60, Alignment::Epoch);
+        let first = block(30, 30, 105);
+        let second = block(100, 110, 210);
+        rollup
+            .consume(StdDecoder::new(BufferedReader::new(first)))
+            .unwrap();
+        rollup
+            .consume(StdDecoder::new(BufferedReader::new(second)))
+            .unwrap();
+        let rollups = rollup.close().unwrap();
+
+        // the first bucket starts before the rollup, the second spans both blocks and the last is
+        // partial
+        assert_eq!(
+            values(rollups.get(Aggregate::Count)),
+            vec![(30, 3.0), (60, 6.0), (120, 6.0), (180, 3.0)]
+        );
+        assert_eq!(
+            values(rollups.get(Aggregate::Sum)),
+            vec![(30, 3.0), (60, 25.0), (120, 21.0), (180, 24.0)]
+        );
+        assert_eq!(
+            values(rollups.get(Aggregate::Min)),
+            vec![(30, 0.0), (60, 0.0), (120, 1.0), (180, 7.0)]
+        );
+        assert_eq!(
+            values(rollups.get(Aggregate::Max)),
+            vec![(30, 2.0), (60, 7.0), (120, 6.0), (180, 9.0)]
+        );
+        assert_eq!(
+            values(rollups.get(Aggregate::Last)),
+            vec![(30, 2.0), (60, 0.0), (120, 6.0), (180, 9.0)]
+        );
+        assert_eq!(
+            values(rollups.get(Aggregate::Avg)),
+            vec![(30, 1.0), (60, 25.0 / 6.0), (120, 3.5), (180, 8.0)]
+        );
+    }
+
+    #[test]
+    fn rollup_start_aligned() {
+        let mut rollup = Rollup::new(25, 60, Alignment::Start);
+        rollup
+            .consume(StdDecoder::new(BufferedReader::new(block(25, 25, 205))))
+            .unwrap();
+
+        // a DataPoint before the current bucket is rejected
+        assert!(rollup.push(DataPoint::new(100, 0.0)).is_err());
+
+        let rollups = rollup.close().unwrap();
+        assert_eq!(
+            values(rollups.get(Aggregate::Count)),
+            vec![(25, 6.0), (85, 6.0), (145, 6.0)]
+        );
+        assert_eq!(
+            values(rollups.get(Aggregate::Last)),
+            vec![(25, 5.0), (85, 11.0), (145, 17.0)]
+        );
+    }
+
+    #[test]
+    fn rollup_sparse_series() {
+        // the first DataPoint is much further from the start of the rollup than the first delta
+        // of a stream can hold
+        let start = 60 * 20_000;
+        let mut rollup = Rollup::new(0, 60, Alignment::Epoch);
+        rollup.push(DataPoint::new(start + 70, 1.0)).unwrap();
+
+        // DataPoints out of order within the current bucket are accepted, the last value is that
+        // of the latest DataPoint
+        rollup.push(DataPoint::new(start + 65, 2.0)).unwrap();
+        rollup.push(DataPoint::new(start + 200, 3.0)).unwrap();
+        let rollups = rollup.close().unwrap();
+
+        assert_eq!(
+            values(rollups.get(Aggregate::Count)),
+            vec![(start + 60, 2.0), (start + 180, 1.0)]
+        );
+        assert_eq!(
+            values(rollups.get(Aggregate::Last)),
+            vec![(start + 60, 1.0), (start + 180, 3.0)]
+        );
+
+        // a rollup without any DataPoints closes to empty streams
+        let rollups = Rollup::new(start, 60, Alignment::Epoch).close().unwrap();
+        assert_eq!(values(rollups.get(Aggregate::Sum)), vec![]);
+    }
+}
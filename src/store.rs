@@ -0,0 +1,810 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use agg;
+use agg::BlockSummary;
+use decode;
+use encode;
+use stream::{BufferedWriter, SliceReader};
+use {DataPoint, Encode, StdDecoder, StdEncoder, TimeUnit};
+
+/// DEFAULT_WINDOW is the length, in seconds, of the blocks used by the Gorilla paper
+pub const DEFAULT_WINDOW: u64 = 2 * 60 * 60;
+
+/// Block
+///
+/// Block is a closed block of a series, which holds the `DataPoint`s whose times fall in a single
+/// window, compressed by a `StdEncoder` whose starting timestamp is the start of the window. Its
+/// bytes are immutable and shared, so cloning a Block is cheap.
+#[derive(Debug, Clone)]
+pub struct Block {
+    start: u64,       // start of the window, inclusive
+    end: u64,         // end of the window, exclusive
+    points: u64,      // number of DataPoints in the block
+    bytes: Arc<[u8]>, // compressed DataPoints
+    summary: BlockSummary,
+}
+
+impl Block {
+    pub(crate) fn new(start: u64, end: u64, bytes: Arc<[u8]>, summary: BlockSummary) -> Self {
+        Block {
+            start,
+            end,
+            points: summary.count(),
+            bytes,
+            summary,
+        }
+    }
+
+    /// Get the start of the window the block covers, which is also the starting timestamp of
+    /// its stream.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Get the end of the window the block covers, which no `DataPoint` in it reaches.
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Get the number of `DataPoint`s in the block.
+    pub fn points(&self) -> u64 {
+        self.points
+    }
+
+    /// Get the compressed bytes of the block, which can be decoded by a `StdDecoder`.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Get the summary of the `DataPoint`s in the block, which answers aggregate queries over
+    /// the whole block without decoding it.
+    pub fn summary(&self) -> &BlockSummary {
+        &self.summary
+    }
+}
+
+// OpenBlock is the block of a series which is currently being written to
+#[derive(Debug)]
+struct OpenBlock {
+    start: u64,
+    end: u64,
+    points: u64,
+    encoder: StdEncoder<BufferedWriter>,
+}
+
+impl OpenBlock {
+    // new creates the block of the window of `window` units of time from `start`, holding `dp`,
+    // or returns an error if `dp` cannot be encoded into it
+    fn new(start: u64, window: u64, unit: TimeUnit, dp: DataPoint) -> Result<Self, encode::Error> {
+        let mut encoder = StdEncoder::with_time_unit(start, unit, BufferedWriter::new());
+        encoder.enable_summary();
+        encoder.try_encode(dp)?;
+
+        Ok(OpenBlock {
+            start,
+            end: start.saturating_add(window),
+            points: 1,
+            encoder,
+        })
+    }
+
+    fn close(self) -> Block {
+        let summary = self.summary();
+        Block {
+            start: self.start,
+            end: self.end,
+            points: self.points,
+            bytes: Arc::from(self.encoder.close()),
+            summary,
+        }
+    }
+
+    // snapshot returns a Block holding a copy of the DataPoints written so far
+    fn snapshot(&self) -> Block {
+        Block {
+            start: self.start,
+            end: self.end,
+            points: self.points,
+            bytes: Arc::from(self.encoder.snapshot()),
+            summary: self.summary(),
+        }
+    }
+
+    fn summary(&self) -> BlockSummary {
+        self.encoder.summary().copied().unwrap_or_default()
+    }
+}
+
+// Series holds the blocks of a single series, ordered by time
+#[derive(Debug, Default)]
+struct Series {
+    closed: Vec<Block>,
+    open: Option<OpenBlock>,
+}
+
+/// SeriesStore
+///
+/// SeriesStore is an append-only, in-memory store of many series, each identified by a key. The
+/// `DataPoint`s of each series are compressed into blocks which cover consecutive windows of time,
+/// aligned to multiples of the window length. Only the latest block of each series is open for
+/// writing, so `DataPoint`s must be inserted in time order within a series.
+#[derive(Debug)]
+pub struct SeriesStore<K> {
+    window: u64,            // length of each block
+    unit: TimeUnit,         // resolution of the timestamps
+    retention: Option<u64>, // how long blocks are kept for by `evict`
+    series: HashMap<K, Series>,
+}
+
+impl<K> SeriesStore<K>
+where
+    K: Eq + Hash,
+{
+    /// new creates a new SeriesStore for timestamps in seconds whose blocks cover `window` seconds
+    pub fn new(window: u64) -> Result<Self, encode::Error> {
+        SeriesStore::with_time_unit(window, TimeUnit::Second)
+    }
+
+    /// with_time_unit creates a new SeriesStore for timestamps with a resolution of `unit` whose
+    /// blocks cover `window` units of time. The window must be short enough for the delta between
+    /// the start of a block and its first `DataPoint` to be encoded, which is just over 4 hours,
+    /// otherwise `encode::Error::FirstDeltaTooLarge` is returned.
+    pub fn with_time_unit(window: u64, unit: TimeUnit) -> Result<Self, encode::Error> {
+        if window > 1 << unit.first_delta_len() {
+            return Err(encode::Error::FirstDeltaTooLarge);
+        }
+
+        Ok(SeriesStore {
+            window: window.max(1),
+            unit,
+            retention: None,
+            series: HashMap::new(),
+        })
+    }
+
+    /// set_retention sets how long blocks are kept for, so that `evict` removes the blocks which
+    /// end more than `retention` before the time it is given
+    pub fn set_retention(&mut self, retention: u64) {
+        self.retention = Some(retention);
+    }
+
+    /// Get the length of the window each block covers.
+    pub fn window(&self) -> u64 {
+        self.window
+    }
+
+    /// Get the resolution of the timestamps in the store.
+    pub fn time_unit(&self) -> TimeUnit {
+        self.unit
+    }
+
+    /// insert appends `dp` to the series identified by `key`, closing its open block and starting
+    /// a new one if `dp` falls in a later window. An error is returned if `dp` is older than the
+    /// last `DataPoint` in the series.
+    pub fn insert(&mut self, key: K, dp: DataPoint) -> Result<(), encode::Error> {
+        let (window, unit) = (self.window, self.unit);
+        let time = dp.get_time();
+        let start = time - time % window;
+
+        let series = match self.series.get_mut(&key) {
+            Some(series) => series,
+            None => {
+                // a new series is only added once dp is encoded, so that a rejected DataPoint
+                // does not leave an empty series behind
+                let open = OpenBlock::new(start, window, unit, dp)?;
+                self.series.insert(
+                    key,
+                    Series {
+                        closed: Vec::new(),
+                        open: Some(open),
+                    },
+                );
+                return Ok(());
+            }
+        };
+
+        let roll = match series.open {
+            Some(ref open) if start < open.start => return Err(encode::Error::OutOfOrder),
+            Some(ref open) => start >= open.end,
+            None => {
+                if series.closed.last().is_some_and(|block| time < block.end) {
+                    return Err(encode::Error::OutOfOrder);
+                }
+                true
+            }
+        };
+
+        if !roll {
+            let open = series.open.as_mut().unwrap();
+            open.encoder.try_encode(dp)?;
+            open.points += 1;
+            return Ok(());
+        }
+
+        // encode dp into the new block before closing the open one, so that the series is left
+        // unchanged if it cannot be encoded
+        let open = OpenBlock::new(start, window, unit, dp)?;
+        if let Some(open) = series.open.take() {
+            series.closed.push(open.close());
+        }
+        series.open = Some(open);
+
+        Ok(())
+    }
+
+    /// query returns the `DataPoint`s of the series identified by `key` whose times are at least
+    /// `start` and less than `end`, decoding only the blocks which overlap that range
+    pub fn query<Q>(&self, key: &Q, start: u64, end: u64) -> Result<Vec<DataPoint>, decode::Error>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let series = match self.series.get(key) {
+            Some(series) => series,
+            None => return Ok(Vec::new()),
+        };
+
+        let in_range = |dp: &Result<DataPoint, decode::Error>| match *dp {
+            Ok(ref dp) => dp.get_time() >= start && dp.get_time() < end,
+            Err(_) => true,
+        };
+
+        let mut dps = Vec::new();
+        for block in &series.closed {
+            if block.end > start && block.start < end {
+                let decoder = StdDecoder::with_time_unit(SliceReader::new(&block.bytes), self.unit);
+                for dp in decoder.filter(in_range) {
+                    dps.push(dp?);
+                }
+            }
+        }
+
+        if let Some(ref open) = series.open {
+            if open.end > start && open.start < end {
+                let r = open.encoder.snapshot_reader();
+                let decoder = StdDecoder::with_time_unit(r, self.unit);
+                for dp in decoder.filter(in_range) {
+                    dps.push(dp?);
+                }
+            }
+        }
+
+        Ok(dps)
+    }
+
+    /// summarize returns a `BlockSummary` of the `DataPoint`s of the series identified by `key`
+    /// whose times are at least `start` and less than `end`. Blocks whose window lies entirely
+    /// within the range are answered from their summary, and only the blocks which straddle
+    /// either end of the range are decoded.
+    pub fn summarize<Q>(&self, key: &Q, start: u64, end: u64) -> Result<BlockSummary, decode::Error>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let mut summary = BlockSummary::new();
+        let series = match self.series.get(key) {
+            Some(series) => series,
+            None => return Ok(summary),
+        };
+
+        for block in &series.closed {
+            if block.start >= start && block.end <= end {
+                summary.merge(&block.summary);
+            } else if block.end > start && block.start < end {
+                let decoder = StdDecoder::with_time_unit(SliceReader::new(&block.bytes), self.unit);
+                summary.merge(&agg::summarize(decoder, start, end)?);
+            }
+        }
+
+        if let Some(ref open) = series.open {
+            if open.start >= start && open.end <= end {
+                summary.merge(&open.summary());
+            } else if open.end > start && open.start < end {
+                let r = open.encoder.snapshot_reader();
+                let decoder = StdDecoder::with_time_unit(r, self.unit);
+                summary.merge(&agg::summarize(decoder, start, end)?);
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// blocks returns the closed blocks of the series identified by `key`, ordered by time
+    pub fn blocks<Q>(&self, key: &Q) -> &[Block]
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.series
+            .get(key)
+            .map(|series| &series.closed[..])
+            .unwrap_or(&[])
+    }
+
+    // overlapping returns the blocks of the series identified by `key` which overlap the range
+    // from `start` to `end`, including a snapshot of the open block
+    fn overlapping<Q>(&self, key: &Q, start: u64, end: u64) -> Vec<Block>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let series = match self.series.get(key) {
+            Some(series) => series,
+            None => return Vec::new(),
+        };
+
+        let mut blocks: Vec<Block> = series
+            .closed
+            .iter()
+            .filter(|block| block.end > start && block.start < end)
+            .cloned()
+            .collect();
+
+        if let Some(ref open) = series.open {
+            if open.end > start && open.start < end {
+                blocks.push(open.snapshot());
+            }
+        }
+
+        blocks
+    }
+
+    /// keys returns an iterator over the keys of every series in the store
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.series.keys()
+    }
+
+    /// evict removes the blocks which end more than the retention before `now`, including open
+    /// blocks, as well as any series left without blocks. It returns the number of blocks
+    /// removed, which is always zero if no retention has been set.
+    pub fn evict(&mut self, now: u64) -> usize {
+        let cutoff = match self.retention {
+            Some(retention) => now.saturating_sub(retention),
+            None => return 0,
+        };
+
+        let mut evicted = 0;
+        for series in self.series.values_mut() {
+            let before = series.closed.len();
+            series.closed.retain(|block| block.end > cutoff);
+            evicted += before - series.closed.len();
+
+            if series.open.as_ref().is_some_and(|open| open.end <= cutoff) {
+                series.open = None;
+                evicted += 1;
+            }
+        }
+
+        self.series
+            .retain(|_, series| !series.closed.is_empty() || series.open.is_some());
+
+        evicted
+    }
+}
+
+/// ShardedSeriesStore
+///
+/// ShardedSeriesStore is a `SeriesStore` which can be shared between threads. Series are divided
+/// between shards by the hash of their key, and each shard has its own read-write lock, so writers
+/// to series in different shards do not contend. Readers share the lock of a shard with each other
+/// and only hold it while they take references to the closed blocks they need and copy the open
+/// block, and decode them after releasing it. A reader sees the open block as it was when the
+/// lock was taken: every `DataPoint` inserted before then and none inserted after.
+#[derive(Debug)]
+pub struct ShardedSeriesStore<K> {
+    shards: Box<[RwLock<SeriesStore<K>>]>,
+    hasher: RandomState,
+}
+
+impl<K> ShardedSeriesStore<K>
+where
+    K: Eq + Hash,
+{
+    /// new creates a new ShardedSeriesStore with `shards` shards for timestamps in seconds whose
+    /// blocks cover `window` seconds
+    pub fn new(shards: usize, window: u64) -> Result<Self, encode::Error> {
+        ShardedSeriesStore::with_time_unit(shards, window, TimeUnit::Second)
+    }
+
+    /// with_time_unit creates a new ShardedSeriesStore with `shards` shards for timestamps with a
+    /// resolution of `unit` whose blocks cover `window` units of time, see
+    /// `SeriesStore::with_time_unit`
+    pub fn with_time_unit(
+        shards: usize,
+        window: u64,
+        unit: TimeUnit,
+    ) -> Result<Self, encode::Error> {
+        let shards = (0..shards.max(1))
+            .map(|_| SeriesStore::with_time_unit(window, unit).map(RwLock::new))
+            .collect::<Result<_, _>>()?;
+
+        Ok(ShardedSeriesStore {
+            shards,
+            hasher: RandomState::new(),
+        })
+    }
+
+    /// set_retention sets how long blocks are kept for, see `SeriesStore::set_retention`
+    pub fn set_retention(&self, retention: u64) {
+        for shard in self.shards.iter() {
+            write(shard).set_retention(retention);
+        }
+    }
+
+    /// insert appends `dp` to the series identified by `key`, see `SeriesStore::insert`
+    pub fn insert(&self, key: K, dp: DataPoint) -> Result<(), encode::Error> {
+        write(self.shard(&key)).insert(key, dp)
+    }
+
+    /// query returns the `DataPoint`s of the series identified by `key` whose times are at least
+    /// `start` and less than `end`, see `SeriesStore::query`. Queries
+    /// only take a read lock, so they do not wait for each other, but do wait for an insert into
+    /// the same shard to finish.
+    pub fn query<Q>(&self, key: &Q, start: u64, end: u64) -> Result<Vec<DataPoint>, decode::Error>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let (blocks, unit) = {
+            let shard = read(self.shard(key));
+            (shard.overlapping(key, start, end), shard.time_unit())
+        };
+
+        let mut dps = Vec::new();
+        for block in &blocks {
+            let decoder = StdDecoder::with_time_unit(SliceReader::new(&block.bytes), unit);
+            for dp in decoder {
+                let dp = dp?;
+                if dp.get_time() >= start && dp.get_time() < end {
+                    dps.push(dp);
+                }
+            }
+        }
+
+        Ok(dps)
+    }
+
+    /// evict removes the blocks which end more than the retention before `now`, see
+    /// `SeriesStore::evict`
+    pub fn evict(&self, now: u64) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| write(shard).evict(now))
+            .sum()
+    }
+
+    fn shard<Q>(&self, key: &Q) -> &RwLock<SeriesStore<K>>
+    where
+        Q: Hash + ?Sized,
+    {
+        let hash = self.hasher.hash_one(key);
+        &self.shards[(hash % self.shards.len() as u64) as usize]
+    }
+}
+
+// read locks a shard for reading, a shard whose lock was poisoned by a panicking thread is still
+// used since every update to a shard leaves it consistent
+fn read<K>(shard: &RwLock<SeriesStore<K>>) -> RwLockReadGuard<'_, SeriesStore<K>> {
+    shard.read().unwrap_or_else(|err| err.into_inner())
+}
+
+// write locks a shard for writing, see `read`
+fn write<K>(shard: &RwLock<SeriesStore<K>>) -> RwLockWriteGuard<'_, SeriesStore<K>> {
+    shard.write().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::{read, SeriesStore, ShardedSeriesStore};
+    use agg::BlockSummary;
+    use encode::Error;
+    use {DataPoint, TimeUnit};
+
+    fn series(start: u64, len: u64, step: u64) -> Vec<DataPoint> {
+        (0..len)
+            .map(|i| DataPoint::new(start + i * step, (i % 10) as f64 * 0.5))
+            .collect()
+    }
+
+    #[test]
+    fn insert_and_query() {
+        let mut store = SeriesStore::new(3600).unwrap();
+
+        let cpu = series(7200 + 5, 1000, 10);
+        let mem = series(3600 * 5, 100, 60);
+        for dp in &cpu {
+            store.insert("cpu", *dp).unwrap();
+        }
+        for dp in &mem {
+            store.insert("mem", *dp).unwrap();
+        }
+
+        // 1000 points every 10 seconds span three blocks, the last of which is still open
+        assert_eq!(store.blocks("cpu").len(), 2);
+        assert_eq!(store.blocks("cpu")[0].start(), 7200);
+        assert_eq!(store.blocks("cpu")[1].points(), 360);
+        assert_eq!(store.blocks("mem").len(), 1);
+        assert_eq!(store.blocks("disk").len(), 0);
+
+        assert_eq!(store.query("cpu", 0, u64::MAX).unwrap(), cpu);
+        assert_eq!(store.query("mem", 0, u64::MAX).unwrap(), mem);
+        assert_eq!(store.query("disk", 0, u64::MAX).unwrap(), vec![]);
+
+        for &(start, end) in &[(0, 7205), (7205, 7206), (9000, 12000), (10795, 16000)] {
+            let expected: Vec<DataPoint> = cpu
+                .iter()
+                .cloned()
+                .filter(|dp| dp.get_time() >= start && dp.get_time() < end)
+                .collect();
+
+            assert_eq!(store.query("cpu", start, end).unwrap(), expected);
+        }
+
+        let mut keys: Vec<&&str> = store.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec![&"cpu", &"mem"]);
+    }
+
+    #[test]
+    fn insert_out_of_order() {
+        let mut store = SeriesStore::with_time_unit(3_600_000, TimeUnit::Millisecond).unwrap();
+
+        store.insert(1, DataPoint::new(7_200_000, 1.0)).unwrap();
+        store.insert(1, DataPoint::new(7_200_500, 1.0)).unwrap();
+
+        // older than the last DataPoint in the open block, or in an earlier window
+        assert_eq!(
+            store
+                .insert(1, DataPoint::new(7_200_499, 1.0))
+                .err()
+                .unwrap(),
+            Error::OutOfOrder
+        );
+        assert_eq!(
+            store
+                .insert(1, DataPoint::new(3_600_000, 1.0))
+                .err()
+                .unwrap(),
+            Error::OutOfOrder
+        );
+
+        // other series are unaffected
+        store.insert(2, DataPoint::new(3_600_000, 1.0)).unwrap();
+
+        assert_eq!(store.query(&1, 0, u64::MAX).unwrap().len(), 2);
+        assert_eq!(store.query(&2, 0, u64::MAX).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn window_too_large() {
+        // the first delta of a block can be up to one less than the window
+        assert!(SeriesStore::<u64>::new(1 << 14).is_ok());
+        assert_eq!(
+            SeriesStore::<u64>::new((1 << 14) + 1).err().unwrap(),
+            Error::FirstDeltaTooLarge
+        );
+        assert_eq!(
+            ShardedSeriesStore::<u64>::with_time_unit(4, 1 << 25, TimeUnit::Millisecond)
+                .err()
+                .unwrap(),
+            Error::FirstDeltaTooLarge
+        );
+
+        // the largest window holds a DataPoint in its last unit of time
+        let mut store = SeriesStore::new(1 << 14).unwrap();
+        store.insert(1, DataPoint::new((1 << 14) - 1, 1.0)).unwrap();
+        store.insert(1, DataPoint::new((2 << 14) - 1, 2.0)).unwrap();
+        assert_eq!(store.blocks(&1).len(), 1);
+
+        // rejected DataPoints leave the store unchanged
+        assert_eq!(
+            store.insert(1, DataPoint::new(1 << 14, 3.0)).err().unwrap(),
+            Error::OutOfOrder
+        );
+        assert_eq!(
+            store.insert(1, DataPoint::new(0, 3.0)).err().unwrap(),
+            Error::OutOfOrder
+        );
+        assert_eq!(store.keys().collect::<Vec<_>>(), vec![&1]);
+        assert_eq!(store.blocks(&1).len(), 1);
+        assert_eq!(
+            store.query(&1, 0, u64::MAX).unwrap(),
+            vec![
+                DataPoint::new((1 << 14) - 1, 1.0),
+                DataPoint::new((2 << 14) - 1, 2.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn evict_blocks() {
+        let mut store = SeriesStore::new(3600).unwrap();
+        for dp in &series(0, 360 * 4, 10) {
+            store.insert("cpu".to_string(), *dp).unwrap();
+        }
+        store
+            .insert("mem".to_string(), DataPoint::new(100, 1.0))
+            .unwrap();
+
+        // nothing is evicted until a retention is set
+        assert_eq!(store.evict(3600 * 10), 0);
+
+        // the first block of cpu is evicted, as is the open block of mem, which removes it
+        store.set_retention(3600 * 2);
+        assert_eq!(store.evict(3600 * 3), 2);
+        assert_eq!(store.blocks("cpu").len(), 2);
+        assert_eq!(store.query("cpu", 0, u64::MAX).unwrap()[0].get_time(), 3600);
+        assert!(store.keys().all(|key| key != "mem"));
+
+        assert_eq!(store.evict(3600 * 10), 3);
+        assert_eq!(store.keys().count(), 0);
+    }
+
+    #[test]
+    fn summarize_range() {
+        let mut store = SeriesStore::new(3600).unwrap();
+        for dp in &series(7200 + 5, 1000, 10) {
+            store.insert("cpu", *dp).unwrap();
+        }
+
+        // ranges which cover whole closed blocks, part of a block, and the open block
+        for &(start, end) in &[(0, u64::MAX), (7200, 14400), (8000, 15000), (16000, 17300)] {
+            let mut expected = BlockSummary::new();
+            for dp in store.query("cpu", start, end).unwrap() {
+                expected.push(dp);
+            }
+
+            assert!(expected.count() > 0);
+            assert_eq!(store.summarize("cpu", start, end).unwrap(), expected);
+        }
+
+        assert_eq!(store.blocks("cpu")[0].summary().count(), 360);
+        assert_eq!(store.summarize("mem", 0, u64::MAX).unwrap().count(), 0);
+    }
+
+    // sharded_value returns the value of the i-th DataPoint of series `key` in the stress test
+    fn sharded_value(key: u64, i: u64) -> f64 {
+        match i % 4 {
+            0 => key as f64,
+            1 => (i as f64).sqrt(),
+            2 => -(i as f64) / 7.0,
+            _ => (i % 13) as f64,
+        }
+    }
+
+    // sharded_reference returns the DataPoints of series `key` in the stress test
+    fn sharded_reference(key: u64, len: u64) -> BTreeMap<u64, f64> {
+        (0..len)
+            .map(|i| (i * 10 + key + i % 7, sharded_value(key, i)))
+            .collect()
+    }
+
+    #[test]
+    fn sharded_readers_share_shard() {
+        let store = ShardedSeriesStore::new(1, 3600).unwrap();
+        store.insert(1, DataPoint::new(10, 1.0)).unwrap();
+
+        // a query does not wait for another reader of the same shard
+        let _guard = read(&store.shards[0]);
+        assert_eq!(
+            store.query(&1, 0, u64::MAX).unwrap(),
+            vec![DataPoint::new(10, 1.0)]
+        );
+    }
+
+    #[test]
+    fn sharded_concurrent_writers_and_readers() {
+        const WRITERS: u64 = 8;
+        const KEYS_PER_WRITER: u64 = 4;
+        const READERS: u64 = 4;
+        const LEN: u64 = 2000;
+
+        let store = Arc::new(ShardedSeriesStore::new(4, 3600).unwrap());
+        let done = Arc::new(AtomicBool::new(false));
+
+        let references: Arc<Vec<BTreeMap<u64, f64>>> = Arc::new(
+            (0..WRITERS * KEYS_PER_WRITER)
+                .map(|key| sharded_reference(key, LEN))
+                .collect(),
+        );
+
+        // each writer owns a set of keys and interleaves writes to them
+        let writers: Vec<_> = (0..WRITERS)
+            .map(|writer| {
+                let store = Arc::clone(&store);
+                let references = Arc::clone(&references);
+                thread::spawn(move || {
+                    let keys: Vec<u64> = (0..KEYS_PER_WRITER)
+                        .map(|k| writer * KEYS_PER_WRITER + k)
+                        .collect();
+                    let series: Vec<Vec<(u64, f64)>> = keys
+                        .iter()
+                        .map(|&key| {
+                            references[key as usize]
+                                .iter()
+                                .map(|(&t, &v)| (t, v))
+                                .collect()
+                        })
+                        .collect();
+
+                    for i in 0..LEN as usize {
+                        for (&key, points) in keys.iter().zip(&series) {
+                            let (time, value) = points[i];
+                            store.insert(key, DataPoint::new(time, value)).unwrap();
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        // a reader may see any prefix of a series, but never a DataPoint which was not written or
+        // a gap before the last DataPoint it sees
+        let readers: Vec<_> = (0..READERS)
+            .map(|reader| {
+                let store = Arc::clone(&store);
+                let references = Arc::clone(&references);
+                let done = Arc::clone(&done);
+                thread::spawn(move || {
+                    let mut seed = reader * 7919 + 1;
+                    let mut queries = 0;
+                    while !done.load(Ordering::Acquire) || queries < 100 {
+                        seed = seed
+                            .wrapping_mul(6364136223846793005)
+                            .wrapping_add(1442695040888963407);
+                        let key = (seed >> 33) % (WRITERS * KEYS_PER_WRITER);
+                        let start = (seed >> 17) % (LEN * 10);
+                        let end = start + (seed >> 7) % (LEN * 5);
+
+                        let actual: Vec<(u64, f64)> = store
+                            .query(&key, start, end)
+                            .unwrap()
+                            .into_iter()
+                            .map(|dp| (dp.get_time(), dp.get_value()))
+                            .collect();
+                        let expected: Vec<(u64, f64)> = references[key as usize]
+                            .range(start..end)
+                            .map(|(&t, &v)| (t, v))
+                            .take(actual.len())
+                            .collect();
+                        assert_eq!(actual, expected);
+
+                        queries += 1;
+                    }
+                })
+            })
+            .collect();
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        done.store(true, Ordering::Release);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        // once every writer has finished, every query sees the whole series
+        for (key, reference) in references.iter().enumerate() {
+            let key = key as u64;
+            for &(start, end) in &[(0, u64::MAX), (5000, 12000), (3599, 3601)] {
+                let actual: Vec<(u64, f64)> = store
+                    .query(&key, start, end)
+                    .unwrap()
+                    .into_iter()
+                    .map(|dp| (dp.get_time(), dp.get_value()))
+                    .collect();
+                let expected: Vec<(u64, f64)> =
+                    reference.range(start..end).map(|(&t, &v)| (t, v)).collect();
+                assert_eq!(actual, expected);
+            }
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use std::boxed::Box;
 
-use stream::{Error, Read};
+use stream::{read_bits_at, Error, Read, Seek};
 use Bit;
 
 /// BufferedReader
 ///
-/// BufferedReader encapsulates a buffer of bytes which can be read from.
+/// BufferedReader encapsulates a buffer of bytes which can be read from. Bits are read a 64-bit
+/// word at a time rather than a byte or bit at a time.
 #[derive(Debug)]
 pub struct BufferedReader {
     bytes: Vec<u8>, // internal buffer of bytes
@@ -23,19 +24,20 @@ impl BufferedReader {
         }
     }
 
-    fn get_byte(&mut self) -> Result<u8, Error> {
-        self.bytes.get(self.index).cloned().ok_or(Error::EOF)
+    // check returns an error if fewer than `num` bits remain in the buffer
+    fn check(&self, num: u32) -> Result<(), Error> {
+        let remaining = (self.bytes.len() - self.index) as u64 * 8 - u64::from(self.pos);
+        if remaining < u64::from(num) {
+            return Err(Error::EOF);
+        }
+
+        Ok(())
     }
 }
 
 impl Read for BufferedReader {
     fn read_bit(&mut self) -> Result<Bit, Error> {
-        if self.pos == 8 {
-            self.index += 1;
-            self.pos = 0;
-        }
-
-        let byte = self.get_byte()?;
+        let byte = self.bytes.get(self.index).cloned().ok_or(Error::EOF)?;
 
         let bit = if byte & 1u8.wrapping_shl(7 - self.pos) == 0 {
             Bit::Zero
@@ -44,32 +46,16 @@ impl Read for BufferedReader {
         };
 
         self.pos += 1;
-
-        Ok(bit)
-    }
-
-    fn read_byte(&mut self) -> Result<u8, Error> {
-        if self.pos == 0 {
-            self.pos += 8;
-            return self.get_byte();
-        }
-
         if self.pos == 8 {
             self.index += 1;
-            return self.get_byte();
+            self.pos = 0;
         }
 
-        let mut byte = 0;
-        let mut b = self.get_byte()?;
-
-        byte |= b.wrapping_shl(self.pos);
-
-        self.index += 1;
-        b = self.get_byte()?;
-
-        byte |= b.wrapping_shr(8 - self.pos);
+        Ok(bit)
+    }
 
-        Ok(byte)
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        self.read_bits(8).map(|byte| byte as u8)
     }
 
     fn read_bits(&mut self, mut num: u32) -> Result<u64, Error> {
@@ -78,21 +64,14 @@ impl Read for BufferedReader {
             num = 64;
         }
 
-        let mut bits: u64 = 0;
-        while num >= 8 {
-            let byte = self.read_byte().map(u64::from)?;
-            bits = bits.wrapping_shl(8) | byte;
-            num -= 8;
-        }
-
-        while num > 0 {
-            self.read_bit()
-                .map(|bit| bits = bits.wrapping_shl(1) | bit.to_u64())?;
-
-            num -= 1;
-        }
+        self.check(num)?;
 
-        Ok(bits)
+        Ok(read_bits_at(
+            &self.bytes,
+            &mut self.index,
+            &mut self.pos,
+            num,
+        ))
     }
 
     fn peak_bits(&mut self, num: u32) -> Result<u64, Error> {
@@ -107,12 +86,40 @@ impl Read for BufferedReader {
 
         Ok(bits)
     }
+
+    fn skip_bits(&mut self, num: u32) -> Result<(), Error> {
+        let num = num.min(64);
+        self.check(num)?;
+
+        let end = self.pos + num;
+        self.index += (end / 8) as usize;
+        self.pos = end % 8;
+
+        Ok(())
+    }
+}
+
+impl Seek for BufferedReader {
+    fn bit_position(&self) -> u64 {
+        self.index as u64 * 8 + u64::from(self.pos)
+    }
+
+    fn seek(&mut self, pos: u64) -> Result<(), Error> {
+        if pos > self.bytes.len() as u64 * 8 {
+            return Err(Error::EOF);
+        }
+
+        self.index = (pos / 8) as usize;
+        self.pos = (pos % 8) as u32;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::BufferedReader;
-    use stream::{Error, Read};
+    use stream::{Error, Read, Seek};
     use Bit;
 
     #[test]
@@ -209,4 +216,35 @@ mod tests {
 
         assert_eq!(b.peak_bits(22).err().unwrap(), Error::EOF);
     }
+
+    #[test]
+    fn read_bits_matches_read_bit() {
+        let bytes: Vec<u8> = (0..600u32).map(|i| (i * 113) as u8).collect();
+        let mut words = BufferedReader::new(bytes.clone().into_boxed_slice());
+        let mut bits = BufferedReader::new(bytes.into_boxed_slice());
+
+        // read every width from 0 to 64 bits, so the words start at every position in a byte
+        for num in (0..65).chain(0..65) {
+            let expected =
+                (0..num).fold(0, |acc, _| (acc << 1) | bits.read_bit().unwrap().to_u64());
+            assert_eq!(words.read_bits(num).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn seek() {
+        let bytes = vec![0b01010111, 0b00011101];
+        let mut b = BufferedReader::new(bytes.into_boxed_slice());
+
+        assert_eq!(b.read_bits(5).unwrap(), 0b01010);
+        assert_eq!(b.bit_position(), 5);
+
+        b.seek(2).unwrap();
+        assert_eq!(b.bit_position(), 2);
+        assert_eq!(b.read_bits(10).unwrap(), 0b0101110001);
+
+        b.seek(16).unwrap();
+        assert_eq!(b.read_bit().err().unwrap(), Error::EOF);
+        assert_eq!(b.seek(17).err().unwrap(), Error::EOF);
+    }
 }
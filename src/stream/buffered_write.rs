@@ -1,12 +1,13 @@
 use std::boxed::Box;
 
-use stream::Write;
+use stream::{Position, Write};
 use Bit;
 
 /// BufferedWriter
 ///
-/// BufferedWriter writes bytes to a buffer.
-#[derive(Debug, Default)]
+/// BufferedWriter writes bytes to a buffer. Multiple bits are written a 64-bit word at a time
+/// rather than a byte or bit at a time.
+#[derive(Debug, Default, Clone)]
 pub struct BufferedWriter {
     buf: Vec<u8>,
     pos: u32, // position in the last byte in the buffer
@@ -30,6 +31,35 @@ impl BufferedWriter {
         }
     }
 
+    /// from_bytes creates a new BufferedWriter which continues writing after the first `bit_len`
+    /// bits of `bytes`, discarding any bits after them. This allows appending to a stream which
+    /// was written, and possibly closed, earlier.
+    pub fn from_bytes(bytes: Box<[u8]>, bit_len: u64) -> Self {
+        let mut buf = bytes.into_vec();
+        let bit_len = bit_len.min(buf.len() as u64 * 8);
+
+        buf.truncate(bit_len.div_ceil(8) as usize);
+
+        // clear any bits in the last byte which follow bit_len
+        let pos = (bit_len % 8) as u32;
+        if pos > 0 {
+            let i = buf.len() - 1;
+            buf[i] &= !(0xffu8 >> pos);
+        }
+
+        BufferedWriter {
+            buf,
+            // a full last byte, or an empty buffer, has no space presently
+            pos: if pos == 0 { 8 } else { pos },
+        }
+    }
+
+    /// get_ref returns a reference to the bytes written so far, the last of which may only be
+    /// partially filled, see `bit_position`
+    pub fn get_ref(&self) -> &[u8] {
+        &self.buf
+    }
+
     fn grow(&mut self) {
         self.buf.push(0);
     }
@@ -81,26 +111,33 @@ impl Write for BufferedWriter {
             num = 64;
         }
 
+        if num == 0 {
+            return;
+        }
+
+        // left align the bits we are writing, discarding everything above the bottom `num` bits
         bits = bits.wrapping_shl(64 - num);
-        while num >= 8 {
-            let byte = bits.wrapping_shr(56);
-            self.write_byte(byte as u8);
 
-            bits = bits.wrapping_shl(8);
-            num -= 8;
-        }
+        // fill the space left in the last byte of the buffer first
+        let space = 8 - self.pos;
+        if space > 0 {
+            let i = self.last_index();
+            self.buf[i] |= (bits >> (56 + self.pos)) as u8;
 
-        while num > 0 {
-            let byte = bits.wrapping_shr(63);
-            if byte == 1 {
-                self.write_bit(Bit::One);
-            } else {
-                self.write_bit(Bit::Zero);
+            if num <= space {
+                self.pos += num;
+                return;
             }
 
-            bits = bits.wrapping_shl(1);
-            num -= 1;
+            bits <<= space;
+            num -= space;
         }
+
+        // then spill the remaining bits, which now start on a byte boundary, as a single word
+        let len = num.div_ceil(8);
+        self.buf
+            .extend_from_slice(&bits.to_be_bytes()[..len as usize]);
+        self.pos = num - (len - 1) * 8;
     }
 
     fn close(self) -> Box<[u8]> {
@@ -108,10 +145,17 @@ impl Write for BufferedWriter {
     }
 }
 
+impl Position for BufferedWriter {
+    fn bit_position(&self) -> u64 {
+        // pos is 8 when the buffer is empty, so this is zero
+        self.buf.len() as u64 * 8 - u64::from(8 - self.pos)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::BufferedWriter;
-    use stream::Write;
+    use stream::{Position, Write};
     use Bit;
 
     #[test]
@@ -247,4 +291,67 @@ mod tests {
         assert_eq!(b.buf[2], 156); // 0b10011100 = 156
         assert_eq!(b.buf[3], 207); // 0b11001111 = 207
     }
+
+    #[test]
+    fn write_bits_matches_write_bit() {
+        let mut words = BufferedWriter::new();
+        let mut bits = BufferedWriter::new();
+
+        // write every width from 0 to 64 bits, so the words start at every position in a byte
+        for num in 0..65 {
+            let value = 0x0123_4567_89ab_cdef_u64.rotate_right(num);
+
+            words.write_bits(value, num);
+            for i in (0..num).rev() {
+                if (value >> i) & 1 == 1 {
+                    bits.write_bit(Bit::One);
+                } else {
+                    bits.write_bit(Bit::Zero);
+                }
+            }
+        }
+
+        assert_eq!(words.pos, bits.pos);
+        assert_eq!(words.buf, bits.buf);
+    }
+
+    #[test]
+    fn bit_position() {
+        let mut b = BufferedWriter::new();
+        assert_eq!(b.bit_position(), 0);
+
+        b.write_bit(Bit::One);
+        assert_eq!(b.bit_position(), 1);
+
+        b.write_byte(9);
+        assert_eq!(b.bit_position(), 9);
+
+        b.write_bits(2508, 7);
+        assert_eq!(b.bit_position(), 16);
+
+        b.write_bits(2508, 64);
+        assert_eq!(b.bit_position(), 80);
+    }
+
+    #[test]
+    fn from_bytes() {
+        let bytes = vec![0b1010_1010, 0b1111_1111, 0b1111_0000];
+
+        let mut b = BufferedWriter::from_bytes(bytes.clone().into_boxed_slice(), 11);
+        assert_eq!(b.bit_position(), 11);
+        b.write_bits(0b00001, 5);
+        assert_eq!(b.close()[..], [0b1010_1010, 0b1110_0001]);
+
+        let mut b = BufferedWriter::from_bytes(bytes.clone().into_boxed_slice(), 8);
+        b.write_bit(Bit::Zero);
+        assert_eq!(b.close()[..], [0b1010_1010, 0]);
+
+        let b = BufferedWriter::from_bytes(bytes.clone().into_boxed_slice(), 100);
+        assert_eq!(b.bit_position(), 24);
+
+        let mut b = BufferedWriter::from_bytes(bytes.into_boxed_slice(), 0);
+        assert_eq!(b.bit_position(), 0);
+        b.write_byte(7);
+        assert_eq!(b.close()[..], [7]);
+    }
 }
@@ -0,0 +1,216 @@
+use std::io;
+
+use stream::{read_bits_at, Error, Read};
+use Bit;
+
+/// DEFAULT_CAPACITY is the size, in bytes, of the refill buffer used by `IoBitReader::new`
+pub const DEFAULT_CAPACITY: usize = 4096;
+
+// MIN_CAPACITY is the smallest refill buffer which can hold the 9 bytes that reading 64 bits
+// from the middle of a byte can span
+const MIN_CAPACITY: usize = 16;
+
+/// IoBitReader
+///
+/// IoBitReader reads bits from an underlying `std::io::Read`, keeping only a small buffer of
+/// bytes in memory which is refilled as bits are consumed.
+#[derive(Debug)]
+pub struct IoBitReader<R: io::Read> {
+    r: R,
+    buf: Box<[u8]>, // refill buffer
+    len: usize,     // number of bytes in buf which were read from r
+    index: usize,   // index into buf
+    pos: u32,       // position in the byte we are currently reading
+}
+
+impl<R> IoBitReader<R>
+where
+    R: io::Read,
+{
+    /// new creates a new IoBitReader which reads bytes from `r`
+    pub fn new(r: R) -> Self {
+        IoBitReader::with_capacity(DEFAULT_CAPACITY, r)
+    }
+
+    /// with_capacity creates a new IoBitReader which reads bytes from `r` into a buffer of at
+    /// least `capacity` bytes
+    pub fn with_capacity(capacity: usize, r: R) -> Self {
+        IoBitReader {
+            r,
+            buf: vec![0; capacity.max(MIN_CAPACITY)].into_boxed_slice(),
+            len: 0,
+            index: 0,
+            pos: 0,
+        }
+    }
+
+    /// get_ref returns a reference to the underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.r
+    }
+
+    // fill ensures there are at least `needed` unread bytes in the buffer
+    fn fill(&mut self, needed: usize) -> Result<(), Error> {
+        if self.len - self.index >= needed {
+            return Ok(());
+        }
+
+        // move the unread bytes to the front of the buffer to make room for new ones
+        self.buf.copy_within(self.index..self.len, 0);
+        self.len -= self.index;
+        self.index = 0;
+
+        while self.len < needed {
+            match self.r.read(&mut self.buf[self.len..]) {
+                Ok(0) => return Err(Error::EOF),
+                Ok(n) => self.len += n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(Error::Io(err)),
+            }
+        }
+
+        Ok(())
+    }
+
+    // bytes_needed returns the number of bytes `num` bits span from the current position
+    fn bytes_needed(&self, num: u32) -> usize {
+        (self.pos + num).div_ceil(8) as usize
+    }
+}
+
+impl<R> Read for IoBitReader<R>
+where
+    R: io::Read,
+{
+    fn read_bit(&mut self) -> Result<Bit, Error> {
+        self.read_bits(1)
+            .map(|bit| if bit == 0 { Bit::Zero } else { Bit::One })
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        self.read_bits(8).map(|byte| byte as u8)
+    }
+
+    fn read_bits(&mut self, mut num: u32) -> Result<u64, Error> {
+        // can't read more than 64 bits into a u64
+        if num > 64 {
+            num = 64;
+        }
+
+        let needed = self.bytes_needed(num);
+        self.fill(needed)?;
+
+        Ok(read_bits_at(&self.buf, &mut self.index, &mut self.pos, num))
+    }
+
+    fn peak_bits(&mut self, num: u32) -> Result<u64, Error> {
+        // fill the buffer first since doing so may move the bytes we are about to read
+        let needed = self.bytes_needed(num.min(64));
+        self.fill(needed)?;
+
+        // save the current index and pos so we can reset them after calling `read_bits`
+        let index = self.index;
+        let pos = self.pos;
+
+        let bits = self.read_bits(num)?;
+
+        self.index = index;
+        self.pos = pos;
+
+        Ok(bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::IoBitReader;
+    use stream::{Error, Read};
+    use Bit;
+
+    // A reader which returns at most one byte from each call to `read`.
+    struct TrickleReader<'a> {
+        bytes: &'a [u8],
+    }
+
+    impl<'a> io::Read for TrickleReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.bytes.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+
+            buf[0] = self.bytes[0];
+            self.bytes = &self.bytes[1..];
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn read_bit() {
+        let bytes = [0b01101100];
+        let mut b = IoBitReader::new(&bytes[..]);
+
+        assert_eq!(b.read_bit().unwrap(), Bit::Zero);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::Zero);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::Zero);
+        assert_eq!(b.read_bit().unwrap(), Bit::Zero);
+
+        assert_eq!(b.read_bit().err().unwrap(), Error::EOF);
+    }
+
+    #[test]
+    fn read_mixed() {
+        let bytes = [0b01101101, 0b01101101];
+        let mut b = IoBitReader::new(TrickleReader { bytes: &bytes });
+
+        assert_eq!(b.read_bit().unwrap(), Bit::Zero);
+        assert_eq!(b.read_bits(3).unwrap(), 0b110);
+        assert_eq!(b.read_byte().unwrap(), 0b11010110);
+        assert_eq!(b.read_bits(2).unwrap(), 0b11);
+        assert_eq!(b.read_bit().unwrap(), Bit::Zero);
+        assert_eq!(b.read_bits(1).unwrap(), 0b1);
+        assert_eq!(b.read_bit().err().unwrap(), Error::EOF);
+    }
+
+    #[test]
+    fn refill_across_reads() {
+        // use the smallest buffer so reads regularly need to move bytes and refill
+        let bytes: Vec<u8> = (0..64).collect();
+        let mut b = IoBitReader::with_capacity(0, TrickleReader { bytes: &bytes });
+
+        assert_eq!(b.read_bits(4).unwrap(), 0);
+        for i in 0..7 {
+            let expected = (0..8).fold(0u64, |acc, j| (acc << 8) | (i * 8 + j));
+            let expected = (expected << 4) | (i * 8 + 8) >> 4;
+
+            assert_eq!(b.peak_bits(64).unwrap(), expected);
+            assert_eq!(b.read_bits(64).unwrap(), expected);
+        }
+
+        assert_eq!(b.read_bits(60).unwrap(), 0x8393a3b3c3d3e3f);
+        assert_eq!(b.read_bits(1).err().unwrap(), Error::EOF);
+    }
+
+    #[test]
+    fn peak_bits() {
+        let bytes = [0b01010111, 0b00011101, 0b11110101, 0b00010100];
+        let mut b = IoBitReader::new(TrickleReader { bytes: &bytes });
+
+        assert_eq!(b.peak_bits(1).unwrap(), 0b0);
+        assert_eq!(b.peak_bits(4).unwrap(), 0b0101);
+        assert_eq!(b.peak_bits(8).unwrap(), 0b01010111);
+        assert_eq!(b.peak_bits(20).unwrap(), 0b01010111000111011111);
+
+        assert_eq!(b.read_bits(12).unwrap(), 0b010101110001);
+
+        assert_eq!(b.peak_bits(1).unwrap(), 0b1);
+        assert_eq!(b.peak_bits(20).unwrap(), 0b11011111010100010100);
+
+        assert_eq!(b.peak_bits(22).err().unwrap(), Error::EOF);
+    }
+}
@@ -0,0 +1,261 @@
+use std::boxed::Box;
+use std::io;
+
+use stream::{Error, Position, Write};
+use Bit;
+
+/// IoBitWriter
+///
+/// IoBitWriter writes bits to an underlying `std::io::Write`, handing each byte to it as soon as
+/// the byte is complete. Since every byte results in a call to the underlying writer, wrapping
+/// unbuffered writers such as files or sockets in a `std::io::BufWriter` is recommended.
+///
+/// Once the underlying writer returns an error the IoBitWriter stops writing, and every
+/// subsequent call to a `try_` method, as well as `try_close`, returns an error of the same kind.
+/// `close` discards such errors, so callers which need to know that every byte was written must
+/// use `try_close` instead.
+#[derive(Debug)]
+pub struct IoBitWriter<W: io::Write> {
+    w: W,
+    byte: u8,     // byte currently being filled
+    pos: u32,     // number of bits written to byte
+    written: u64, // number of bytes handed to w
+
+    err: Option<io::ErrorKind>, // kind of the first error returned by w
+}
+
+impl<W> IoBitWriter<W>
+where
+    W: io::Write,
+{
+    /// new creates a new IoBitWriter which writes bytes to `w`
+    pub fn new(w: W) -> Self {
+        IoBitWriter {
+            w,
+            byte: 0,
+            pos: 0,
+            written: 0,
+            err: None,
+        }
+    }
+
+    /// get_ref returns a reference to the underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.w
+    }
+
+    /// finish writes the last partially filled byte, padded with zeroes, flushes the underlying
+    /// writer and returns it
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.check()?;
+
+        if self.pos > 0 {
+            self.emit()?;
+        }
+
+        self.w.flush()?;
+        Ok(self.w)
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        match self.err {
+            Some(kind) => Err(Error::Io(io::Error::from(kind))),
+            None => Ok(()),
+        }
+    }
+
+    fn emit(&mut self) -> Result<(), Error> {
+        let byte = self.byte;
+        self.byte = 0;
+        self.pos = 0;
+
+        match self.w.write_all(&[byte]) {
+            Ok(()) => {
+                self.written += 1;
+                Ok(())
+            }
+            Err(err) => {
+                self.err = Some(err.kind());
+                Err(Error::Io(err))
+            }
+        }
+    }
+}
+
+// The infallible methods ignore errors from the underlying writer since they are remembered by the
+// IoBitWriter and returned by any later call to `try_close`.
+impl<W> Write for IoBitWriter<W>
+where
+    W: io::Write,
+{
+    fn write_bit(&mut self, bit: Bit) {
+        let _ = self.try_write_bit(bit);
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        let _ = self.try_write_byte(byte);
+    }
+
+    fn write_bits(&mut self, bits: u64, num: u32) {
+        let _ = self.try_write_bits(bits, num);
+    }
+
+    // Close the IoBitWriter, the returned slice is always empty since every byte has already been
+    // handed to the underlying writer. Any error from the underlying writer, including one from
+    // writing the last byte or flushing, is discarded; use `try_close` to observe it.
+    fn close(self) -> Box<[u8]> {
+        let _ = self.finish();
+        Box::new([])
+    }
+
+    fn try_write_bit(&mut self, bit: Bit) -> Result<(), Error> {
+        self.try_write_bits(bit.to_u64(), 1)
+    }
+
+    fn try_write_byte(&mut self, byte: u8) -> Result<(), Error> {
+        self.try_write_bits(u64::from(byte), 8)
+    }
+
+    fn try_write_bits(&mut self, bits: u64, mut num: u32) -> Result<(), Error> {
+        self.check()?;
+
+        // we should never write more than 64 bits for a u64
+        if num > 64 {
+            num = 64;
+        }
+
+        while num > 0 {
+            // fill as much of the current byte as we can with the most significant bits left
+            let n = num.min(8 - self.pos);
+            let chunk = bits.wrapping_shr(num - n) & ((1 << n) - 1);
+            self.byte |= (chunk as u8).wrapping_shl(8 - self.pos - n);
+
+            self.pos += n;
+            num -= n;
+
+            if self.pos == 8 {
+                self.emit()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn try_close(self) -> Result<Box<[u8]>, Error> {
+        self.finish().map(|_| Box::new([]) as Box<[u8]>)
+    }
+}
+
+impl<W> Position for IoBitWriter<W>
+where
+    W: io::Write,
+{
+    fn bit_position(&self) -> u64 {
+        self.written * 8 + u64::from(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::IoBitWriter;
+    use stream::{BufferedWriter, Error, Position, Write};
+    use Bit;
+
+    // A writer which accepts `limit` bytes before returning errors.
+    struct LimitedWriter {
+        limit: usize,
+    }
+
+    impl io::Write for LimitedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.limit == 0 {
+                return Err(io::Error::new(io::ErrorKind::WriteZero, "limit reached"));
+            }
+
+            let n = buf.len().min(self.limit);
+            self.limit -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_bits_completes_bytes() {
+        let mut buf = Vec::new();
+
+        {
+            let mut b = IoBitWriter::new(&mut buf);
+
+            // 101011
+            b.write_bits(43, 6);
+            assert_eq!(b.get_ref().len(), 0);
+
+            // 010
+            b.write_bits(2, 3);
+            assert_eq!(b.get_ref().len(), 1);
+
+            // 1
+            b.write_bits(1, 1);
+
+            // 1010 1100 1110 0011 1101
+            b.write_bits(708157, 20);
+
+            // 11
+            b.write_bits(3, 2);
+            assert_eq!(b.get_ref().len(), 4);
+
+            b.finish().unwrap();
+        }
+
+        assert_eq!(buf, vec![173, 107, 56, 247]);
+    }
+
+    #[test]
+    fn matches_buffered_writer() {
+        let mut buf = Vec::new();
+        let mut i = IoBitWriter::new(&mut buf);
+        let mut b = BufferedWriter::new();
+
+        for n in 1..65 {
+            let bits = 0xdead_beef_cafe_f00d_u64.rotate_left(n);
+
+            i.write_bits(bits, n);
+            b.write_bits(bits, n);
+
+            i.write_bit(Bit::One);
+            b.write_bit(Bit::One);
+
+            i.write_byte(n as u8);
+            b.write_byte(n as u8);
+        }
+
+        assert_eq!(i.try_close().unwrap().len(), 0);
+        assert_eq!(buf[..], b.close()[..]);
+    }
+
+    #[test]
+    fn surfaces_io_errors() {
+        let mut b = IoBitWriter::new(LimitedWriter { limit: 1 });
+
+        assert!(b.try_write_byte(1).is_ok());
+        assert!(b.try_write_bits(0b1010, 4).is_ok());
+
+        let err = b.try_write_bits(0b1010, 4).err().unwrap();
+        assert_eq!(err, Error::Io(io::ErrorKind::WriteZero.into()));
+
+        // only the byte accepted by the underlying writer is counted
+        assert_eq!(b.bit_position(), 8);
+
+        // the infallible methods remember the error so it can be reported when closing
+        b.write_byte(7);
+        assert_eq!(
+            b.try_close().err().unwrap(),
+            Error::Io(io::ErrorKind::WriteZero.into())
+        );
+    }
+}
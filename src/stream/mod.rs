@@ -1,31 +1,53 @@
-use std::{error, fmt};
+use std::{error, fmt, io};
 
 use Bit;
 
 /// Error
 ///
 /// Enum used to represent potential errors when interacting with a stream.
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub enum Error {
+    /// There were not enough bits left in the stream to read.
     EOF,
+    /// The underlying reader returned an error.
+    Io(io::Error),
+}
+
+impl PartialEq for Error {
+    fn eq(&self, other: &Error) -> bool {
+        match (self, other) {
+            (Error::EOF, Error::EOF) => true,
+            // io::Error does not implement PartialEq so the best we can do is compare kinds
+            (Error::Io(a), Error::Io(b)) => a.kind() == b.kind(),
+            _ => false,
+        }
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::EOF => write!(f, "Encountered the end of the stream"),
+            Error::Io(ref err) => write!(f, "Encountered an I/O error: {}", err),
         }
     }
 }
 
 impl error::Error for Error {
-    fn description(&self) -> &str {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
-            Error::EOF => "Encountered the end of the stream",
+            Error::EOF => None,
+            Error::Io(ref err) => Some(err),
         }
     }
 }
 
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
 /// Read
 ///
 /// Read is a trait that encapsulates the functionality required to read from a stream of bytes.
@@ -41,6 +63,33 @@ pub trait Read {
 
     /// Get the next `num` bits, but do not update place in stream.
     fn peak_bits(&mut self, num: u32) -> Result<u64, Error>;
+
+    /// Skip past the next `num` bits, where `num` is at most 64, without returning them.
+    fn skip_bits(&mut self, num: u32) -> Result<(), Error> {
+        self.read_bits(num).map(|_| ())
+    }
+}
+
+/// Seek
+///
+/// Seek is a trait for streams which can be read from an arbitrary position, so that a decoder
+/// can resume from a known point in the stream.
+pub trait Seek {
+    /// Get the number of bits read from the start of the underlying stream.
+    fn bit_position(&self) -> u64;
+
+    /// Move to `pos` bits from the start of the underlying stream, so that the bit there is the
+    /// next one read.
+    fn seek(&mut self, pos: u64) -> Result<(), Error>;
+}
+
+/// Position
+///
+/// Position is a trait for writers which know how many bits have been written to them, so that an
+/// encoder can check that it is resumed with the bytes it wrote.
+pub trait Position {
+    /// Get the number of bits written to the underlying stream.
+    fn bit_position(&self) -> u64;
 }
 
 /// Write
@@ -58,10 +107,98 @@ pub trait Write {
 
     // Close the underlying stream and return a pointer to the array of bytes.
     fn close(self) -> Box<[u8]>;
+
+    /// Write a single bit to the underlying stream, returning any error encountered.
+    fn try_write_bit(&mut self, bit: Bit) -> Result<(), Error> {
+        self.write_bit(bit);
+        Ok(())
+    }
+
+    /// Write a single byte to the underlying stream, returning any error encountered.
+    fn try_write_byte(&mut self, byte: u8) -> Result<(), Error> {
+        self.write_byte(byte);
+        Ok(())
+    }
+
+    /// Write the bottom `num` bits of `bits` to the underlying stream, returning any error
+    /// encountered.
+    fn try_write_bits(&mut self, bits: u64, num: u32) -> Result<(), Error> {
+        self.write_bits(bits, num);
+        Ok(())
+    }
+
+    /// Close the underlying stream, returning any error encountered while writing to it.
+    fn try_close(self) -> Result<Box<[u8]>, Error>
+    where
+        Self: Sized,
+    {
+        Ok(self.close())
+    }
+}
+
+// read_bits_at reads `num` bits, where `num` is at most 64, from `bytes` starting at bit `pos` of
+// the byte at `index` and advances `index` and `pos` past them. The caller must ensure `bytes` is
+// long enough.
+fn read_bits_at(bytes: &[u8], index: &mut usize, pos: &mut u32, num: u32) -> u64 {
+    if num == 0 {
+        return 0;
+    }
+
+    let bits = if *index + 8 <= bytes.len() {
+        // load the next 64 bits as a single word, left aligning the bits we have not read yet
+        let mut word = [0; 8];
+        word.copy_from_slice(&bytes[*index..*index + 8]);
+        let mut bits = u64::from_be_bytes(word) << *pos;
+
+        // the bits may spill into a ninth byte if we are in the middle of a byte
+        if num > 64 - *pos {
+            bits |= u64::from(bytes[*index + 8]) >> (8 - *pos);
+        }
+
+        bits >> (64 - num)
+    } else {
+        // near the end of the bytes fall back to reading a byte at a time
+        let mut bits: u64 = 0;
+        let mut i = *index;
+        let mut p = *pos;
+        let mut remaining = num;
+        while remaining > 0 {
+            // take as many of the remaining bits as we can from the current byte
+            let available = 8 - p;
+            let n = remaining.min(available);
+            let byte = u64::from(bytes[i]);
+
+            bits = bits.wrapping_shl(n) | (byte.wrapping_shr(available - n) & ((1 << n) - 1));
+
+            remaining -= n;
+            p = 0;
+            i += 1;
+        }
+
+        bits
+    };
+
+    let end = *pos + num;
+    *index += (end / 8) as usize;
+    *pos = end % 8;
+
+    bits
 }
 
 pub mod buffered_write;
 pub use self::buffered_write::BufferedWriter;
 
+pub mod io_write;
+pub use self::io_write::IoBitWriter;
+
 pub mod buffered_read;
 pub use self::buffered_read::BufferedReader;
+
+pub mod io_read;
+pub use self::io_read::IoBitReader;
+
+pub mod slice_read;
+pub use self::slice_read::SliceReader;
+
+pub mod snapshot_read;
+pub use self::snapshot_read::SnapshotReader;
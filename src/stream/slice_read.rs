@@ -0,0 +1,190 @@
+use stream::{read_bits_at, Error, Read, Seek};
+use Bit;
+
+/// SliceReader
+///
+/// SliceReader reads bits from a borrowed slice of bytes, which allows decoding blocks held in
+/// memory-mapped files or shared buffers without copying them.
+#[derive(Debug, Clone)]
+pub struct SliceReader<'a> {
+    bytes: &'a [u8], // borrowed bytes
+    index: usize,    // index into bytes
+    pos: u32,        // position in the byte we are currently reading
+}
+
+impl<'a> SliceReader<'a> {
+    /// new creates a new `SliceReader` which reads from `bytes`
+    pub fn new(bytes: &'a [u8]) -> Self {
+        SliceReader {
+            bytes,
+            index: 0,
+            pos: 0,
+        }
+    }
+
+    // check returns an error if fewer than `num` bits remain in the slice
+    fn check(&self, num: u32) -> Result<(), Error> {
+        let remaining = (self.bytes.len() - self.index) as u64 * 8 - u64::from(self.pos);
+        if remaining < u64::from(num) {
+            return Err(Error::EOF);
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Read for SliceReader<'a> {
+    fn read_bit(&mut self) -> Result<Bit, Error> {
+        self.read_bits(1)
+            .map(|bit| if bit == 0 { Bit::Zero } else { Bit::One })
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        self.read_bits(8).map(|byte| byte as u8)
+    }
+
+    fn read_bits(&mut self, mut num: u32) -> Result<u64, Error> {
+        // can't read more than 64 bits into a u64
+        if num > 64 {
+            num = 64;
+        }
+
+        self.check(num)?;
+
+        Ok(read_bits_at(
+            self.bytes,
+            &mut self.index,
+            &mut self.pos,
+            num,
+        ))
+    }
+
+    fn peak_bits(&mut self, num: u32) -> Result<u64, Error> {
+        // save the current index and pos so we can reset them after calling `read_bits`
+        let index = self.index;
+        let pos = self.pos;
+
+        let bits = self.read_bits(num)?;
+
+        self.index = index;
+        self.pos = pos;
+
+        Ok(bits)
+    }
+
+    fn skip_bits(&mut self, num: u32) -> Result<(), Error> {
+        let num = num.min(64);
+        self.check(num)?;
+
+        let end = self.pos + num;
+        self.index += (end / 8) as usize;
+        self.pos = end % 8;
+
+        Ok(())
+    }
+}
+
+impl<'a> Seek for SliceReader<'a> {
+    fn bit_position(&self) -> u64 {
+        self.index as u64 * 8 + u64::from(self.pos)
+    }
+
+    fn seek(&mut self, pos: u64) -> Result<(), Error> {
+        if pos > self.bytes.len() as u64 * 8 {
+            return Err(Error::EOF);
+        }
+
+        self.index = (pos / 8) as usize;
+        self.pos = (pos % 8) as u32;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SliceReader;
+    use stream::{Error, Read};
+    use Bit;
+
+    #[test]
+    fn read_bit() {
+        let bytes = [0b01101100, 0b11101001];
+        let mut b = SliceReader::new(&bytes);
+
+        assert_eq!(b.read_bit().unwrap(), Bit::Zero);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::Zero);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::Zero);
+        assert_eq!(b.read_bit().unwrap(), Bit::Zero);
+
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::Zero);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::Zero);
+        assert_eq!(b.read_bit().unwrap(), Bit::Zero);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+
+        assert_eq!(b.read_bit().err().unwrap(), Error::EOF);
+    }
+
+    #[test]
+    fn read_byte() {
+        let bytes = [100, 25, 0, 240, 240];
+        let mut b = SliceReader::new(&bytes);
+
+        assert_eq!(b.read_byte().unwrap(), 100);
+        assert_eq!(b.read_byte().unwrap(), 25);
+        assert_eq!(b.read_byte().unwrap(), 0);
+
+        // read some individual bits we can test `read_byte` when the position in the
+        // byte we are currently reading is non-zero
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+        assert_eq!(b.read_bit().unwrap(), Bit::One);
+
+        assert_eq!(b.read_byte().unwrap(), 15);
+
+        assert_eq!(b.read_byte().err().unwrap(), Error::EOF);
+    }
+
+    #[test]
+    fn read_bits() {
+        let bytes = [0b01010111, 0b00011101, 0b11110101, 0b00010100];
+        let mut b = SliceReader::new(&bytes);
+
+        assert_eq!(b.read_bits(3).unwrap(), 0b010);
+        assert_eq!(b.read_bits(1).unwrap(), 0b1);
+        assert_eq!(b.read_bits(20).unwrap(), 0b01110001110111110101);
+        assert_eq!(b.read_bits(8).unwrap(), 0b00010100);
+        assert_eq!(b.read_bits(4).err().unwrap(), Error::EOF);
+    }
+
+    #[test]
+    fn peak_bits() {
+        let bytes = [0b01010111, 0b00011101, 0b11110101, 0b00010100];
+        let mut b = SliceReader::new(&bytes);
+
+        assert_eq!(b.peak_bits(1).unwrap(), 0b0);
+        assert_eq!(b.peak_bits(4).unwrap(), 0b0101);
+        assert_eq!(b.peak_bits(8).unwrap(), 0b01010111);
+        assert_eq!(b.peak_bits(20).unwrap(), 0b01010111000111011111);
+
+        // read some individual bits we can test `peak_bits` when the position in the
+        // byte we are currently reading is non-zero
+        assert_eq!(b.read_bits(12).unwrap(), 0b010101110001);
+
+        assert_eq!(b.peak_bits(1).unwrap(), 0b1);
+        assert_eq!(b.peak_bits(4).unwrap(), 0b1101);
+        assert_eq!(b.peak_bits(8).unwrap(), 0b11011111);
+        assert_eq!(b.peak_bits(20).unwrap(), 0b11011111010100010100);
+
+        assert_eq!(b.peak_bits(22).err().unwrap(), Error::EOF);
+    }
+}
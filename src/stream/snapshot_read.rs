@@ -0,0 +1,142 @@
+use stream::{read_bits_at, Error, Read, Seek};
+use Bit;
+
+/// SnapshotReader
+///
+/// SnapshotReader reads bits from a borrowed prefix of bytes followed by a short owned tail. It
+/// allows the bytes of an open `StdEncoder` to be decoded without copying them, with the tail
+/// holding the last partially filled byte and the end marker, see `StdEncoder::snapshot_reader`.
+#[derive(Debug, Clone)]
+pub struct SnapshotReader<'a> {
+    prefix: &'a [u8], // borrowed bytes, all of which are read before tail
+    tail: Box<[u8]>,  // owned bytes which follow prefix
+    index: usize,     // index into prefix followed by tail
+    pos: u32,         // position in the byte we are currently reading
+}
+
+impl<'a> SnapshotReader<'a> {
+    /// new creates a new `SnapshotReader` which reads from `prefix` and then from `tail`
+    pub fn new(prefix: &'a [u8], tail: Box<[u8]>) -> Self {
+        SnapshotReader {
+            prefix,
+            tail,
+            index: 0,
+            pos: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.prefix.len() + self.tail.len()
+    }
+
+    // check returns an error if fewer than `num` bits remain
+    fn check(&self, num: u32) -> Result<(), Error> {
+        let remaining = (self.len() - self.index) as u64 * 8 - u64::from(self.pos);
+        if remaining < u64::from(num) {
+            return Err(Error::EOF);
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Read for SnapshotReader<'a> {
+    fn read_bit(&mut self) -> Result<Bit, Error> {
+        self.read_bits(1)
+            .map(|bit| if bit == 0 { Bit::Zero } else { Bit::One })
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        self.read_bits(8).map(|byte| byte as u8)
+    }
+
+    fn read_bits(&mut self, mut num: u32) -> Result<u64, Error> {
+        // can't read more than 64 bits into a u64
+        if num > 64 {
+            num = 64;
+        }
+
+        self.check(num)?;
+
+        let split = self.prefix.len();
+        if self.index >= split {
+            let mut index = self.index - split;
+            let bits = read_bits_at(&self.tail, &mut index, &mut self.pos, num);
+            self.index = index + split;
+            return Ok(bits);
+        }
+
+        // read as many bits as we can from the prefix, then the rest from the start of the tail
+        let available = (split - self.index) as u64 * 8 - u64::from(self.pos);
+        let from_prefix = num.min(available as u32);
+        let mut bits = read_bits_at(self.prefix, &mut self.index, &mut self.pos, from_prefix);
+
+        let from_tail = num - from_prefix;
+        if from_tail > 0 {
+            let mut index = 0;
+            bits = (bits << from_tail)
+                | read_bits_at(&self.tail, &mut index, &mut self.pos, from_tail);
+            self.index = index + split;
+        }
+
+        Ok(bits)
+    }
+
+    fn peak_bits(&mut self, num: u32) -> Result<u64, Error> {
+        // save the current index and pos so we can reset them after calling `read_bits`
+        let index = self.index;
+        let pos = self.pos;
+
+        let bits = self.read_bits(num)?;
+
+        self.index = index;
+        self.pos = pos;
+
+        Ok(bits)
+    }
+}
+
+impl<'a> Seek for SnapshotReader<'a> {
+    fn bit_position(&self) -> u64 {
+        self.index as u64 * 8 + u64::from(self.pos)
+    }
+
+    fn seek(&mut self, pos: u64) -> Result<(), Error> {
+        if pos > self.len() as u64 * 8 {
+            return Err(Error::EOF);
+        }
+
+        self.index = (pos / 8) as usize;
+        self.pos = (pos % 8) as u32;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SnapshotReader;
+    use stream::{Error, Read, SliceReader};
+
+    #[test]
+    fn read_across_tail() {
+        let bytes: Vec<u8> = (0..40u32).map(|i| (i * 113) as u8).collect();
+
+        // every width read from every position matches reading the same bytes as one slice
+        for split in 0..bytes.len() {
+            for num in 1..65 {
+                let tail = bytes[split..].to_vec().into_boxed_slice();
+                let mut actual = SnapshotReader::new(&bytes[..split], tail);
+                let mut expected = SliceReader::new(&bytes);
+
+                assert_eq!(actual.read_bits(3).unwrap(), expected.read_bits(3).unwrap());
+                while let Ok(bits) = expected.read_bits(num) {
+                    assert_eq!(actual.peak_bits(num).unwrap(), bits);
+                    assert_eq!(actual.read_bits(num).unwrap(), bits);
+                }
+
+                assert_eq!(actual.read_bits(num).err().unwrap(), Error::EOF);
+            }
+        }
+    }
+}
@@ -0,0 +1,672 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::{error, fmt, io};
+
+use encode;
+use file::crc32;
+use store::SeriesStore;
+use stream;
+use DataPoint;
+
+/// DEFAULT_SEGMENT_SIZE is the size, in bytes, a segment may reach before the log moves on to a
+/// new one
+pub const DEFAULT_SEGMENT_SIZE: u64 = 64 * 1024 * 1024;
+
+// SEGMENT_EXTENSION is the extension of the files which hold the segments of a log
+const SEGMENT_EXTENSION: &str = "wal";
+
+// RECORD_HEADER_LEN is the length, in bytes, of the payload length and checksum which precede
+// the payload of every record
+const RECORD_HEADER_LEN: usize = 8;
+
+/// Error
+///
+/// Error encapsulates the potential errors that can be encountered when writing to or replaying a
+/// write-ahead log
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The underlying files could not be written to or read from.
+    Stream(stream::Error),
+    /// The series key is too long to store in a record.
+    KeyTooLong,
+    /// The segment with the sequence number holds an invalid record which was not cut short by a
+    /// crash.
+    Corrupt(u64),
+    /// A replayed `DataPoint` could not be inserted into a `SeriesStore`.
+    Encode(encode::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Stream(ref err) => write!(f, "Stream error: {}", err),
+            Error::KeyTooLong => write!(f, "Series key is too long to log"),
+            Error::Corrupt(seq) => write!(f, "Log segment {} is corrupt", seq),
+            Error::Encode(ref err) => write!(f, "Encode error: {}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Stream(ref err) => Some(err),
+            Error::Encode(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Stream(stream::Error::Io(err))
+    }
+}
+
+impl From<encode::Error> for Error {
+    fn from(err: encode::Error) -> Error {
+        Error::Encode(err)
+    }
+}
+
+// Segment is a single file of the log
+#[derive(Debug)]
+struct Segment {
+    seq: u64,              // sequence number, which orders segments and names the file
+    len: u64,              // length of the valid records in the file in bytes
+    max_time: Option<u64>, // latest time of a DataPoint in the segment
+}
+
+/// Wal
+///
+/// Wal is a write-ahead log of the `DataPoint`s inserted into the open blocks of a store, so that
+/// they can be recovered after a crash. The log is a directory of segments, each of which is a
+/// sequence of records, and a new segment is started once the current one reaches the segment
+/// size. A record is the length and CRC-32 of its payload, followed by the payload: the length of
+/// the series key, the key, and the time and value bits of the `DataPoint`. Every integer is
+/// stored big-endian.
+///
+/// Records are buffered until `sync` is called, so only records written before the last call to
+/// `sync` are certain to survive a crash. A crash may leave a partially written record, or a tail
+/// of zero bytes, at the end of the last segment, which is discarded when the log is opened.
+#[derive(Debug)]
+pub struct Wal {
+    dir: PathBuf,
+    segment_size: u64,
+    segments: Vec<Segment>, // every segment, ordered by sequence number, the last is written to
+    w: BufWriter<File>,
+}
+
+impl Wal {
+    /// open opens the log in `dir`, creating the directory if it does not exist
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        Wal::with_segment_size(dir, DEFAULT_SEGMENT_SIZE)
+    }
+
+    /// with_segment_size opens the log in `dir` like `open`, starting a new segment whenever the
+    /// current one would grow beyond `segment_size` bytes
+    pub fn with_segment_size<P: AsRef<Path>>(dir: P, segment_size: u64) -> Result<Self, Error> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut seqs = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == SEGMENT_EXTENSION) {
+                let seq = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str()?.parse().ok());
+                if let Some(seq) = seq {
+                    seqs.push(seq);
+                }
+            }
+        }
+        seqs.sort_unstable();
+
+        let mut segments = Vec::with_capacity(seqs.len());
+        for (i, &seq) in seqs.iter().enumerate() {
+            let path = segment_path(&dir, seq);
+            let bytes = fs::read(&path)?;
+
+            let mut len = 0;
+            let mut max_time = None;
+            let end = for_each_record(&bytes, |consumed, _, dp| {
+                len = consumed as u64;
+                max_time = max_time.max(Some(dp.get_time()));
+            });
+
+            match end {
+                End::Complete => {}
+                End::Partial if i + 1 == seqs.len() => {
+                    // the last segment was cut short by a crash, so drop the torn record to let
+                    // new records follow the last complete one
+                    OpenOptions::new().write(true).open(&path)?.set_len(len)?;
+                }
+                // an invalid record which is not at the end of the log may be followed by valid
+                // records, so it is left for the caller to deal with rather than truncated
+                _ => return Err(Error::Corrupt(seq)),
+            }
+
+            segments.push(Segment { seq, len, max_time });
+        }
+
+        if segments.is_empty() {
+            File::create(segment_path(&dir, 0))?;
+            segments.push(Segment {
+                seq: 0,
+                len: 0,
+                max_time: None,
+            });
+        }
+
+        let active = segments[segments.len() - 1].seq;
+        let w = BufWriter::new(
+            OpenOptions::new()
+                .append(true)
+                .open(segment_path(&dir, active))?,
+        );
+
+        Ok(Wal {
+            dir,
+            segment_size,
+            segments,
+            w,
+        })
+    }
+
+    /// append logs that `dp` was inserted into the series identified by `key`
+    pub fn append(&mut self, key: &str, dp: DataPoint) -> Result<(), Error> {
+        if key.len() > usize::from(u16::MAX) {
+            return Err(Error::KeyTooLong);
+        }
+
+        let mut payload = Vec::with_capacity(2 + key.len() + 16);
+        payload.extend_from_slice(&(key.len() as u16).to_be_bytes());
+        payload.extend_from_slice(key.as_bytes());
+        payload.extend_from_slice(&dp.get_time().to_be_bytes());
+        payload.extend_from_slice(&dp.get_value().to_bits().to_be_bytes());
+
+        let len = (RECORD_HEADER_LEN + payload.len()) as u64;
+        let active = &self.segments[self.segments.len() - 1];
+        if active.len > 0 && active.len + len > self.segment_size {
+            self.rotate()?;
+        }
+
+        self.w.write_all(&(payload.len() as u32).to_be_bytes())?;
+        self.w.write_all(&crc32(&payload).to_be_bytes())?;
+        self.w.write_all(&payload)?;
+
+        let last = self.segments.len() - 1;
+        let active = &mut self.segments[last];
+        active.len += len;
+        active.max_time = active.max_time.max(Some(dp.get_time()));
+
+        Ok(())
+    }
+
+    /// sync writes any buffered records to the current segment and waits for them to reach the
+    /// disk
+    pub fn sync(&mut self) -> Result<(), Error> {
+        self.w.flush()?;
+        self.w.get_ref().sync_data()?;
+        Ok(())
+    }
+
+    /// rotate syncs the current segment and starts a new one, so that the records written so
+    /// far can be removed by `truncate`
+    pub fn rotate(&mut self) -> Result<(), Error> {
+        self.sync()?;
+
+        let seq = self.segments[self.segments.len() - 1].seq + 1;
+        self.w = BufWriter::new(File::create(segment_path(&self.dir, seq))?);
+        self.segments.push(Segment {
+            seq,
+            len: 0,
+            max_time: None,
+        });
+
+        Ok(())
+    }
+
+    /// truncate removes the oldest segments, other than the one being written to, whose
+    /// `DataPoint`s are all before `time`. It should be called with the start of the oldest open
+    /// block once the blocks before it have been flushed, and returns the number of segments
+    /// removed.
+    pub fn truncate(&mut self, time: u64) -> Result<usize, Error> {
+        let removable = self.segments[..self.segments.len() - 1]
+            .iter()
+            .take_while(|segment| segment.max_time.is_none_or(|max| max < time))
+            .count();
+
+        for segment in &self.segments[..removable] {
+            fs::remove_file(segment_path(&self.dir, segment.seq))?;
+        }
+        self.segments.drain(..removable);
+
+        Ok(removable)
+    }
+
+    /// replay calls `f` with the series key and `DataPoint` of every record in the log, in the
+    /// order they were appended, and returns the number of records
+    pub fn replay<F>(&mut self, mut f: F) -> Result<u64, Error>
+    where
+        F: FnMut(&str, DataPoint),
+    {
+        self.w.flush()?;
+
+        let mut count = 0;
+        for segment in &self.segments {
+            let mut bytes = fs::read(segment_path(&self.dir, segment.seq))?;
+            bytes.truncate(segment.len as usize);
+
+            let end = for_each_record(&bytes, |_, key, dp| {
+                count += 1;
+                f(key, dp);
+            });
+            if end != End::Complete {
+                return Err(Error::Corrupt(segment.seq));
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// recover replays the log into `store`, which rebuilds the open blocks of its series, and
+    /// returns the number of records inserted. `flushed` is called with the key of each series
+    /// and returns the time before which its blocks have already been written out, and records
+    /// before it are skipped so that those blocks are not built again. Since `truncate` only
+    /// removes whole segments the log usually still holds some of them. `|_| 0` replays every
+    /// record.
+    pub fn recover<F>(
+        &mut self,
+        store: &mut SeriesStore<String>,
+        mut flushed: F,
+    ) -> Result<u64, Error>
+    where
+        F: FnMut(&str) -> u64,
+    {
+        let mut count = 0;
+        let mut result = Ok(());
+        self.replay(|key, dp| {
+            if result.is_ok() && dp.get_time() >= flushed(key) {
+                result = store.insert(key.to_string(), dp);
+                count += 1;
+            }
+        })?;
+        result?;
+
+        Ok(count)
+    }
+}
+
+// segment_path returns the path of the segment with sequence number `seq` in `dir`
+fn segment_path(dir: &Path, seq: u64) -> PathBuf {
+    dir.join(format!("{:020}.{}", seq, SEGMENT_EXTENSION))
+}
+
+// End describes how the valid records at the start of a segment end
+#[derive(Debug, PartialEq)]
+enum End {
+    Complete, // every byte belongs to a valid record
+    Partial,  // the last record was torn by a crash, see `is_torn`
+    Invalid,  // a record does not match its checksum or is malformed
+}
+
+// for_each_record calls `f` with the number of bytes consumed so far, the series key and the
+// DataPoint of each valid record at the start of `bytes`, and returns how the valid records end
+fn for_each_record<F>(mut bytes: &[u8], mut f: F) -> End
+where
+    F: FnMut(usize, &str, DataPoint),
+{
+    let total = bytes.len();
+    while !bytes.is_empty() {
+        let record = match parse_record(bytes) {
+            Some(record) => record,
+            None if is_torn(bytes) => return End::Partial,
+            None => return End::Invalid,
+        };
+
+        let (len, key, dp) = record;
+        bytes = &bytes[len..];
+        f(total - bytes.len(), key, dp);
+    }
+
+    End::Complete
+}
+
+// is_torn returns whether the invalid record at the start of `bytes` could have been left by a
+// crash while it was written: it is the last record, or runs past the end, or it and everything
+// after it are zero bytes, as a file which was extended before its data reached the disk ends
+fn is_torn(bytes: &[u8]) -> bool {
+    if bytes.len() < RECORD_HEADER_LEN || bytes.iter().all(|&byte| byte == 0) {
+        return true;
+    }
+
+    let len = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    RECORD_HEADER_LEN + len >= bytes.len()
+}
+
+// parse_record parses the record at the start of `bytes`, returning its length, series key and
+// DataPoint, or None if it is incomplete or does not match its checksum
+fn parse_record(bytes: &[u8]) -> Option<(usize, &str, DataPoint)> {
+    let header = bytes.get(..RECORD_HEADER_LEN)?;
+    let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let checksum = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+
+    let payload = bytes.get(RECORD_HEADER_LEN..RECORD_HEADER_LEN.checked_add(len)?)?;
+    if crc32(payload) != checksum || payload.len() < 18 {
+        return None;
+    }
+
+    let key_len = usize::from(u16::from_be_bytes([payload[0], payload[1]]));
+    if payload.len() != 2 + key_len + 16 {
+        return None;
+    }
+    let key = std::str::from_utf8(&payload[2..2 + key_len]).ok()?;
+
+    let mut time = [0; 8];
+    let mut value = [0; 8];
+    time.copy_from_slice(&payload[2 + key_len..10 + key_len]);
+    value.copy_from_slice(&payload[10 + key_len..]);
+    let dp = DataPoint::new(
+        u64::from_be_bytes(time),
+        f64::from_bits(u64::from_be_bytes(value)),
+    );
+
+    Some((RECORD_HEADER_LEN + len, key, dp))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::error;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{segment_path, Error, Wal, RECORD_HEADER_LEN};
+    use file::{FileReader, FileWriter};
+    use store::SeriesStore;
+    use DataPoint;
+    use {encode, stream};
+
+    // test_dir returns an empty directory for the test named `name`
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tsz-wal-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn records() -> Vec<(String, DataPoint)> {
+        (0..300u64)
+            .map(|i| {
+                let key = format!("host-{}.cpu", i % 3);
+                (key, DataPoint::new(1000 + i * 10, i as f64 / 4.0 - 7.0))
+            })
+            .collect()
+    }
+
+    fn replay(wal: &mut Wal) -> Vec<(String, DataPoint)> {
+        let mut replayed = Vec::new();
+        wal.replay(|key, dp| replayed.push((key.to_string(), dp)))
+            .unwrap();
+        replayed
+    }
+
+    #[test]
+    fn append_and_replay() {
+        let dir = test_dir("replay");
+        let records = records();
+
+        {
+            let mut wal = Wal::with_segment_size(&dir, 1024).unwrap();
+            for (key, dp) in &records {
+                wal.append(key, *dp).unwrap();
+            }
+            wal.sync().unwrap();
+            assert!(wal.segments.len() > 1);
+            assert_eq!(replay(&mut wal), records);
+        }
+
+        // reopening the log finds every segment
+        let mut wal = Wal::with_segment_size(&dir, 1024).unwrap();
+        assert_eq!(replay(&mut wal), records);
+
+        let mut store = SeriesStore::new(3600).unwrap();
+        assert_eq!(
+            wal.recover(&mut store, |_| 0).unwrap(),
+            records.len() as u64
+        );
+        let expected: Vec<DataPoint> = records
+            .iter()
+            .filter(|(key, _)| key == "host-1.cpu")
+            .map(|(_, dp)| *dp)
+            .collect();
+        assert_eq!(store.query("host-1.cpu", 0, u64::MAX).unwrap(), expected);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replay_after_torn_write() {
+        let dir = test_dir("torn");
+        let records = records();
+
+        let last = {
+            let mut wal = Wal::with_segment_size(&dir, 1024).unwrap();
+            for (key, dp) in &records {
+                wal.append(key, *dp).unwrap();
+            }
+            wal.sync().unwrap();
+            wal.segments[wal.segments.len() - 1].seq
+        };
+
+        // cut the last record of the last segment short, as a crash while writing it would
+        let path = segment_path(&dir, last);
+        let len = fs::metadata(&path).unwrap().len();
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 5)
+            .unwrap();
+
+        // every complete record is recovered and new records follow them
+        let mut wal = Wal::with_segment_size(&dir, 1024).unwrap();
+        assert_eq!(replay(&mut wal), records[..records.len() - 1].to_vec());
+
+        let (key, dp) = records[records.len() - 1].clone();
+        wal.append(&key, dp).unwrap();
+        wal.sync().unwrap();
+        let mut wal = Wal::with_segment_size(&dir, 1024).unwrap();
+        assert_eq!(replay(&mut wal), records);
+
+        // a record whose checksum does not match is torn if it is the last one
+        let mut bytes = fs::read(&path).unwrap();
+        let len = bytes.len();
+        bytes[len - 1] ^= 0x01;
+        fs::write(&path, &bytes).unwrap();
+        let mut wal = Wal::with_segment_size(&dir, 1024).unwrap();
+        assert_eq!(replay(&mut wal), records[..records.len() - 1].to_vec());
+        let record_len = RECORD_HEADER_LEN + 2 + key.len() + 16;
+        assert_eq!(
+            fs::metadata(&path).unwrap().len(),
+            (len - record_len) as u64
+        );
+
+        let (key, dp) = records[records.len() - 1].clone();
+        wal.append(&key, dp).unwrap();
+        wal.sync().unwrap();
+
+        // as are zero bytes after the last record, which a crash leaves if the file was extended
+        // but the record never reached the disk
+        let mut bytes = fs::read(&path).unwrap();
+        let len = bytes.len();
+        bytes.extend_from_slice(&[0; 100]);
+        fs::write(&path, &bytes).unwrap();
+        let mut wal = Wal::with_segment_size(&dir, 1024).unwrap();
+        assert_eq!(replay(&mut wal), records);
+        assert_eq!(fs::metadata(&path).unwrap().len(), len as u64);
+
+        // damage to a segment other than the last is not mistaken for a torn write
+        let first = wal.segments[0].seq;
+        let mut bytes = fs::read(segment_path(&dir, first)).unwrap();
+        bytes[20] ^= 0xff;
+        fs::write(segment_path(&dir, first), bytes).unwrap();
+        assert_eq!(
+            Wal::with_segment_size(&dir, 1024).err().unwrap(),
+            Error::Corrupt(first)
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_last_segment() {
+        let dir = test_dir("corrupt");
+        let records = records();
+
+        let last = {
+            let mut wal = Wal::with_segment_size(&dir, 1024).unwrap();
+            for (key, dp) in &records {
+                wal.append(key, *dp).unwrap();
+            }
+            wal.sync().unwrap();
+            wal.segments[wal.segments.len() - 1].seq
+        };
+
+        // flipping a bit in the value of a record in the middle of the last segment is not
+        // mistaken for a torn write, so the complete records after it are kept
+        let path = segment_path(&dir, last);
+        let mut bytes = fs::read(&path).unwrap();
+        let record_len = RECORD_HEADER_LEN + 2 + records[0].0.len() + 16;
+        let middle = (bytes.len() / record_len / 2 + 1) * record_len - 1;
+        bytes[middle] ^= 0x01;
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(
+            Wal::with_segment_size(&dir, 1024).err().unwrap(),
+            Error::Corrupt(last)
+        );
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+
+        bytes[middle] ^= 0x01;
+        fs::write(&path, &bytes).unwrap();
+        let mut wal = Wal::with_segment_size(&dir, 1024).unwrap();
+        assert_eq!(replay(&mut wal), records);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn recover_after_flush() {
+        let dir = test_dir("flush");
+        let records = records();
+
+        // write the closed blocks of each series to a block file, as a store would before
+        // truncating the log, and note the time they were written up to
+        let mut store = SeriesStore::new(600).unwrap();
+        let mut wal = Wal::with_segment_size(&dir, 1024).unwrap();
+        for (key, dp) in &records {
+            wal.append(key, *dp).unwrap();
+            store.insert(key.clone(), *dp).unwrap();
+        }
+        wal.sync().unwrap();
+
+        let mut writer = FileWriter::new(Vec::new()).unwrap();
+        let mut keys: Vec<String> = store.keys().cloned().collect();
+        keys.sort();
+        let mut flushed = HashMap::new();
+        for key in &keys {
+            for block in store.blocks(key) {
+                writer.append_block(key, block).unwrap();
+                flushed.insert(key.clone(), block.end());
+            }
+        }
+        let bytes = writer.finish().unwrap();
+        let oldest_open = *flushed.values().min().unwrap();
+        assert!(wal.truncate(oldest_open).unwrap() > 0);
+        drop(wal);
+
+        // the segments left still hold flushed records, which are not recovered a second time
+        let mut wal = Wal::with_segment_size(&dir, 1024).unwrap();
+        assert!(replay(&mut wal)[0].1.get_time() < oldest_open);
+
+        let mut store = SeriesStore::new(600).unwrap();
+        let count = wal.recover(&mut store, |key| flushed[key]).unwrap();
+        let reader = FileReader::open(&bytes).unwrap();
+        for key in &keys {
+            assert!(store.blocks(key).is_empty());
+
+            let mut actual = Vec::new();
+            for decoder in reader.decoders(key, 0, u64::MAX).unwrap() {
+                actual.extend(decoder.map(|dp| dp.unwrap()));
+            }
+            assert!(actual.iter().all(|dp| dp.get_time() < flushed[key]));
+
+            let recovered = store.query(key, 0, u64::MAX).unwrap();
+            assert!(recovered.iter().all(|dp| dp.get_time() >= flushed[key]));
+            actual.extend(recovered);
+
+            let expected: Vec<DataPoint> = records
+                .iter()
+                .filter(|(k, _)| k == key)
+                .map(|(_, dp)| *dp)
+                .collect();
+            assert_eq!(actual, expected);
+        }
+        let expected = records
+            .iter()
+            .filter(|(key, dp)| dp.get_time() >= flushed[key])
+            .count();
+        assert_eq!(count, expected as u64);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn error_source() {
+        let err = Error::from(encode::Error::Stream(stream::Error::EOF));
+        let source = error::Error::source(&err).unwrap();
+        assert_eq!(
+            source.downcast_ref::<encode::Error>(),
+            Some(&encode::Error::Stream(stream::Error::EOF))
+        );
+        assert_eq!(
+            source.source().unwrap().downcast_ref::<stream::Error>(),
+            Some(&stream::Error::EOF)
+        );
+        assert!(error::Error::source(&Error::KeyTooLong).is_none());
+    }
+
+    #[test]
+    fn truncate_flushed_segments() {
+        let dir = test_dir("truncate");
+        let records = records();
+
+        let mut wal = Wal::with_segment_size(&dir, 1024).unwrap();
+        for (key, dp) in &records {
+            wal.append(key, *dp).unwrap();
+        }
+        let segments = wal.segments.len();
+
+        // nothing is removed while every segment holds a DataPoint at or after the time
+        assert_eq!(wal.truncate(1000).unwrap(), 0);
+
+        let removed = wal.truncate(2000).unwrap();
+        assert!(removed > 0);
+        assert_eq!(wal.segments.len(), segments - removed);
+
+        // the records left include every record at or after the time
+        let replayed = replay(&mut wal);
+        assert!(replayed.len() < records.len());
+        assert_eq!(replayed[..], records[records.len() - replayed.len()..]);
+        assert!(replayed[0].1.get_time() < 2000);
+
+        // the segment being written to is only removed once it has been rotated
+        wal.rotate().unwrap();
+        assert_eq!(wal.truncate(u64::MAX).unwrap(), segments - removed);
+        assert!(replay(&mut wal).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}